@@ -1,13 +1,20 @@
+mod apply;
+mod exitcode;
+mod json;
+
 use cargo_scan::effect::Effect;
-use cargo_scan::scanner;
+use cargo_scan::ident::CanonicalPath;
+use cargo_scan::scanner::{self, ActiveConfig, ScanResults};
 
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term;
@@ -17,10 +24,19 @@ use inquire::{
     formatter::MultiOptionFormatter, list_option::ListOption, validator::Validation,
     MultiSelect,
 };
+use log::warn;
 use serde::{Deserialize, Serialize};
 
 // TODO: Consider switching to tui-rs (might be more heavyweight than we need)
 
+/// How to print discovered effects: a human-readable codespan diagnostic, or
+/// one JSON object per line (JSON Lines) on stdout for CI/dashboard use.
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
 #[derive(Parser, Debug)]
 struct Config {
     #[clap(long = "lines-before", default_value_t = 4)]
@@ -41,11 +57,56 @@ struct Args {
     /// path to the check file (will create a new one if it doesn't existJ)
     check_path: PathBuf,
 
+    /// Also resolve the crate's full transitive dependency graph and scan
+    /// every dependency's sources, attributing each effect to the crate
+    /// + version it was found in
+    #[clap(long = "include-deps")]
+    include_deps: bool,
+
+    /// How to print discovered effects
+    #[clap(long = "message-format", value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// Don't prompt; instead re-scan the crate and diff the discovered
+    /// effects against the check file, failing if any are new or still
+    /// marked Skipped. Exits non-zero so this can gate CI.
+    #[clap(long = "check", alias = "non-interactive")]
+    non_interactive: bool,
+
+    /// Turn reviewed (Safe/CallerChecked) statuses into source annotations,
+    /// writing the audited crate's files back to disk
+    #[clap(long = "apply")]
+    apply: bool,
+
+    /// Number of threads to scan with (defaults to the number of CPUs)
+    #[clap(long = "jobs", short = 'j')]
+    jobs: Option<usize>,
+
     #[clap(flatten)]
     /// Optional config args
     config: Config,
 }
 
+/// Identifies the package (name + version) that an effect was discovered in.
+///
+/// When `--include-deps` isn't passed, every effect is attributed to the
+/// root crate being vetted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+struct CrateId {
+    name: String,
+    version: String,
+}
+
+impl CrateId {
+    fn root(crate_path: &PathBuf) -> Result<Self> {
+        let data = cargo_scan::util::load_cargo_toml(crate_path)?;
+        Ok(CrateId {
+            name: data.name,
+            version: data.version,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 enum CheckStatus {
     Skipped,
@@ -54,10 +115,24 @@ enum CheckStatus {
     CallerChecked,
 }
 
+impl CheckStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skipped => "skipped",
+            Self::Safe => "safe",
+            Self::Unsafe => "unsafe",
+            Self::CallerChecked => "caller_checked",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct AnnotatedEffect {
     effect: Effect,
     check: CheckStatus,
+    /// The package + version this effect was discovered in. Lets the check
+    /// file stay valid across re-scans as long as this isn't bumped.
+    krate: CrateId,
 }
 
 // TODO: Include information about crate/version
@@ -72,7 +147,11 @@ struct CheckFile {
 impl CheckFile {
     fn new(p: PathBuf) -> Self {
         // TODO: hash the file
-        CheckFile { effects: Vec::new(), base_dir: p, hash: 0 }
+        CheckFile {
+            effects: Vec::new(),
+            base_dir: p,
+            hash: 0,
+        }
     }
 
     fn save_to_file(&self, p: PathBuf) -> Result<()> {
@@ -102,19 +181,204 @@ fn get_check_file(check_filepath: PathBuf, crate_filepath: PathBuf) -> Result<Ch
     Ok(check_file)
 }
 
-fn get_effects(p: &PathBuf) -> Result<Vec<Effect>> {
+fn get_effects(p: &PathBuf) -> Result<Vec<(CrateId, Effect)>> {
+    let krate = CrateId::root(p)?;
     let scanner_res = scanner::load_and_scan(p);
     // TODO: There's a lot of stuff in the scan right now that isn't included
     //       in the effects. We should make sure we're reporting everything we
     //       care about.
-    Ok(scanner_res.effects)
+    Ok(scanner_res
+        .effects
+        .into_iter()
+        .map(|e| (krate.clone(), e))
+        .collect())
+}
+
+/// Resolve the crate's full transitive dependency graph via the cargo API
+/// and return, for each dependency package, the package identifier and the
+/// directory its checked-out sources live in -- similar to siderophile's
+/// trawl step.
+fn resolve_dependency_sources(crate_path: &PathBuf) -> Result<Vec<(CrateId, PathBuf)>> {
+    use cargo::core::Workspace;
+    use cargo::ops;
+    use cargo::util::config::Config as CargoConfig;
+
+    let cargo_config = CargoConfig::default()?;
+    let manifest_path = crate_path.join("Cargo.toml");
+    let ws = Workspace::new(&manifest_path, &cargo_config)?;
+    let root_name = ws.current()?.name().to_string();
+
+    let (package_set, resolve) = ops::resolve_ws(&ws)?;
+    // `resolve_ws` only resolves the dependency graph; it doesn't download
+    // registry sources. Without this, `get_one` below errors on any
+    // dependency that isn't already present locally (e.g. a registry dep
+    // not yet downloaded).
+    let pkg_ids: Vec<_> = resolve.iter().collect();
+    package_set.get_many(pkg_ids.iter().copied())?;
+
+    let mut sources = Vec::new();
+    for pkg_id in pkg_ids {
+        if pkg_id.name().as_str() == root_name {
+            // The root crate is scanned separately, via get_effects.
+            continue;
+        }
+        let pkg = package_set.get_one(pkg_id)?;
+        let krate = CrateId {
+            name: pkg_id.name().to_string(),
+            version: pkg_id.version().to_string(),
+        };
+        sources.push((krate, pkg.root().to_path_buf()));
+    }
+    Ok(sources)
+}
+
+/// Scan the crate at `p` plus, when `include_deps` is set, its entire
+/// transitive dependency closure, attributing each effect to the crate
+/// name + version it came from.
+fn get_effects_all(p: &PathBuf, include_deps: bool) -> Result<Vec<(CrateId, Effect)>> {
+    let mut effects = get_effects(p)?;
+    if include_deps {
+        for (krate, src_dir) in resolve_dependency_sources(p)? {
+            let scanner_res = scanner::load_and_scan(&src_dir);
+            effects.extend(scanner_res.effects.into_iter().map(|e| (krate.clone(), e)));
+        }
+    }
+    Ok(effects)
+}
+
+/// Scan the root crate plus (when `include_deps` is set) its full
+/// dependency closure into one `ScanResults` with a call graph that spans
+/// crate boundaries, by caching each crate's scan to its own file under
+/// `target/cargo-scan-cache` and merging them -- see
+/// `scanner::merge_serialized_crates`. This is what lets a sink found in a
+/// dependency be traced to its transitive callers in the root crate (or a
+/// sibling dependency), which `get_effects_all`'s per-crate effect list
+/// can't express on its own.
+fn scan_merged_call_graph(crate_path: &PathBuf, include_deps: bool) -> Result<ScanResults> {
+    let cache_dir = crate_path.join("target").join("cargo-scan-cache");
+    fs::create_dir_all(&cache_dir)?;
+
+    let root_cache = cache_dir.join("root.json");
+    scanner::scan_crate_to_file(
+        crate_path,
+        HashSet::new(),
+        ActiveConfig::none(),
+        &root_cache,
+    )?;
+    let mut cache_paths = vec![root_cache];
+
+    if include_deps {
+        for (krate, src_dir) in resolve_dependency_sources(crate_path)? {
+            let dep_cache = cache_dir.join(format!("{}-{}.json", krate.name, krate.version));
+            scanner::scan_crate_to_file(
+                &src_dir,
+                HashSet::new(),
+                ActiveConfig::none(),
+                &dep_cache,
+            )?;
+            cache_paths.push(dep_cache);
+        }
+    }
+
+    let cache_paths: Vec<&Path> = cache_paths.iter().map(PathBuf::as_path).collect();
+    scanner::merge_serialized_crates(&cache_paths)
+}
+
+/// For every effect in the merged cross-crate call graph, the functions that
+/// transitively reach it, sorted for stable output -- e.g. a sink flagged in
+/// a dependency together with the root crate's functions that actually call
+/// into it. Empty (not an error) when `include_deps` is off, or when
+/// building the merged call graph fails (warns and degrades to no
+/// propagation report, same as the rest of this binary's best-effort
+/// cross-crate features).
+fn cross_crate_propagation(
+    crate_path: &PathBuf,
+    include_deps: bool,
+) -> Vec<(CanonicalPath, Vec<CanonicalPath>)> {
+    if !include_deps {
+        return Vec::new();
+    }
+    match scan_merged_call_graph(crate_path, include_deps) {
+        Ok(merged) => merged
+            .effects
+            .iter()
+            .filter_map(|effect| {
+                let mut callers: Vec<CanonicalPath> = merged
+                    .transitive_callers(effect.callee())
+                    .into_iter()
+                    .collect();
+                if callers.is_empty() {
+                    return None;
+                }
+                callers.sort_by_key(CanonicalPath::to_string);
+                Some((effect.callee().clone(), callers))
+            })
+            .collect(),
+        Err(err) => {
+            warn!("Could not build merged cross-crate call graph: {}", err);
+            Vec::new()
+        }
+    }
 }
 
-fn print_effect_info(effect: Effect, config: &Config) -> Result<()> {
+/// Actually show `cross_crate_propagation`'s result to the user -- a sink
+/// that's transitively reachable across crate boundaries is as actionable a
+/// finding as a directly-discovered effect, so it gets printed the same way
+/// `print_effect_info` prints an effect, respecting `--message-format`
+/// rather than only going out through a log line nothing reads by default.
+fn print_cross_crate_propagation(
+    propagated: &[(CanonicalPath, Vec<CanonicalPath>)],
+    format: &MessageFormat,
+) {
+    for (sink, callers) in propagated {
+        if *format == MessageFormat::Json {
+            let record = json::JsonCrossCratePropagation {
+                sink: sink.to_string(),
+                callers: callers.iter().map(CanonicalPath::to_string).collect(),
+            };
+            match serde_json::to_string(&record) {
+                Ok(line) => println!("{}", line),
+                Err(err) => warn!(
+                    "Could not serialize cross-crate propagation record: {}",
+                    err
+                ),
+            }
+        } else {
+            println!(
+                "{} is transitively reachable from {} caller(s) across crate boundaries:",
+                sink,
+                callers.len()
+            );
+            for caller in callers {
+                println!("    {}", caller);
+            }
+        }
+    }
+}
+
+/// Find the status this effect was last annotated with in the loaded check
+/// file, if any -- used to surface it in JSON output.
+fn find_check_status<'a>(
+    check_file: &'a CheckFile,
+    effect: &Effect,
+) -> Option<&'a CheckStatus> {
+    check_file
+        .effects
+        .iter()
+        .find(|ae| &ae.effect == effect)
+        .map(|ae| &ae.check)
+}
+
+fn print_effect_info(
+    effect: Effect,
+    config: &Config,
+    format: &MessageFormat,
+    check_status: Option<&CheckStatus>,
+) -> Result<()> {
     let mut full_path = effect.call_loc().dir().clone();
     full_path.push(effect.call_loc().file());
 
-    let src_contents = std::fs::read_to_string(full_path)?;
+    let src_contents = std::fs::read_to_string(&full_path)?;
 
     // Get the byte ranges for each line of the src file
     let src_lines = src_contents.split("\n");
@@ -126,6 +390,7 @@ fn print_effect_info(effect: Effect, config: &Config) -> Result<()> {
 
     // calculate the byte ranges for the effect
     let effect_line = effect.call_loc().line();
+    let effect_col = effect.call_loc().column();
     let bounded_start_line =
         std::cmp::max(effect_line - config.lines_before_effect as usize, 0);
     let bounded_end_line = std::cmp::min(
@@ -135,13 +400,49 @@ fn print_effect_info(effect: Effect, config: &Config) -> Result<()> {
 
     let surrounding_start = src_linenum_ranges.get(&bounded_start_line).unwrap().0;
     let surrounding_end = src_linenum_ranges.get(&bounded_end_line).unwrap().1;
-    let effect_start = src_linenum_ranges.get(&effect_line).unwrap().0;
-    let effect_end = src_linenum_ranges.get(&effect_line).unwrap().1;
+    // The effect's own span, not the whole line it's on: start at its
+    // column and run for the length of the pattern text matched at the
+    // call site (e.g. `fs::read_to_string`), the same text `pattern()`
+    // reports.
+    let effect_line_start = src_linenum_ranges.get(&effect_line).unwrap().0;
+    let pattern_len = effect.pattern().as_ref().len();
+    let effect_start = effect_line_start + effect_col;
+    let effect_end = effect_start + pattern_len;
+
+    if *format == MessageFormat::Json {
+        let span = json::JsonSpan {
+            byte_start: effect_start,
+            byte_end: effect_end,
+            line_start: effect_line,
+            line_end: effect_line,
+            col_start: effect_col,
+            col_end: effect_col + pattern_len,
+        };
+        let snippet_span = json::JsonSpan {
+            byte_start: surrounding_start,
+            byte_end: surrounding_end,
+            line_start: bounded_start_line,
+            line_end: bounded_end_line,
+            col_start: 0,
+            col_end: 0,
+        };
+        let line = json::effect_to_json_line(
+            &effect,
+            &full_path,
+            span,
+            snippet_span,
+            check_status.map(CheckStatus::as_str),
+        )?;
+        println!("{}", line);
+        return Ok(());
+    }
 
     // TODO: cache files?
     let mut files = SimpleFiles::new();
-    let file_id =
-        files.add(format!("{}", effect.call_loc().file().display()), src_contents);
+    let file_id = files.add(
+        format!("{}", effect.call_loc().file().display()),
+        src_contents,
+    );
 
     // construct the codespan diagnostic
     // TODO: make this a better effect message
@@ -180,35 +481,94 @@ fn get_user_check() -> Result<CheckStatus> {
             ["c"] => Ok(CheckStatus::CallerChecked),
             ["l"] => Ok(CheckStatus::Skipped),
             _ => Err(anyhow!("Invalid user input somehow")),
-        }
+        },
         Err(_) => Err(anyhow!("Couldn't succusefully prompt the user")),
     }
 }
 
-fn main() {
-    let args = Args::parse();
-
-    let mut check_file =
-        match get_check_file(args.check_path.clone(), args.crate_path.clone()) {
-            Ok(c) => c,
-            Err(e) => {
-                println!("err: {:?}", e);
-                return;
+/// Diff the discovered effects against the check file without prompting.
+/// Any effect that's new, or still marked `Skipped`, is a failure.
+fn run_check_mode(check_file: &CheckFile, effects: &[(CrateId, Effect)]) -> i32 {
+    let mut unreviewed = 0;
+    for (krate, effect) in effects {
+        match find_check_status(check_file, effect) {
+            Some(CheckStatus::Safe | CheckStatus::Unsafe | CheckStatus::CallerChecked) => {}
+            Some(CheckStatus::Skipped) | None => {
+                println!(
+                    "unreviewed effect in {}-{}: {:?}",
+                    krate.name,
+                    krate.version,
+                    effect.pattern().as_ref()
+                );
+                unreviewed += 1;
             }
-        };
-    let effects = get_effects(&args.crate_path).unwrap();
+        }
+    }
+
+    if unreviewed > 0 {
+        println!("{} unreviewed effect(s) found", unreviewed);
+        exitcode::DATAERR
+    } else {
+        println!("all effects accounted for");
+        exitcode::OK
+    }
+}
+
+fn run(args: Args) -> Result<i32> {
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
+
+    let mut check_file = get_check_file(args.check_path.clone(), args.crate_path.clone())?;
+    let effects = get_effects_all(&args.crate_path, args.include_deps)?;
+    let propagated = cross_crate_propagation(&args.crate_path, args.include_deps);
+    print_cross_crate_propagation(&propagated, &args.message_format);
+
+    if args.non_interactive {
+        return Ok(run_check_mode(&check_file, &effects));
+    }
 
     // TODO: Figure out how to check this incrementally; resume from
     //       partially checked file
     // Iterate through the effects and prompt the user for if they're safe
-    for e in effects {
-        if print_effect_info(e, &args.config).is_err() {
+    for (krate, e) in effects {
+        let prior_status = find_check_status(&check_file, &e);
+        if print_effect_info(e.clone(), &args.config, &args.message_format, prior_status)
+            .is_err()
+        {
             println!("Error printing effect information. Trying to continue...");
         }
         let status = get_user_check();
         // Add the annotated effect to the new effect file
+        if let Ok(check) = status {
+            check_file.effects.push(AnnotatedEffect {
+                effect: e,
+                check,
+                krate,
+            });
+        }
     }
 
     // save the new check file
-    check_file.save_to_file(args.check_path.clone());
+    check_file.save_to_file(args.check_path.clone())?;
+
+    if args.apply {
+        apply::apply(&check_file.effects)?;
+    }
+
+    Ok(exitcode::OK)
+}
+
+fn main() {
+    let args = Args::parse();
+    let code = match run(args) {
+        Ok(code) => code,
+        Err(e) => {
+            println!("err: {:?}", e);
+            exitcode::IOERR
+        }
+    };
+    std::process::exit(code);
 }
@@ -0,0 +1,135 @@
+//! Turn reviewed effect statuses into concrete source edits, rustfix-style,
+//! so a review leaves an in-code audit trail instead of only an external
+//! check file.
+
+use super::{AnnotatedEffect, CheckStatus};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::warn;
+
+/// A single text insertion into a source file, keyed by byte offset.
+struct Suggestion {
+    byte_offset: usize,
+    /// 1-indexed source line, used only to name the line in a collision
+    /// warning; insertion itself is purely byte-offset based.
+    line: usize,
+    text: String,
+}
+
+/// The audit-comment to insert above a call site, or `None` if this status
+/// shouldn't leave a source annotation.
+fn comment_for(effect: &AnnotatedEffect) -> Option<String> {
+    match effect.check {
+        CheckStatus::Safe => Some(format!(
+            "// cargo-scan: audited safe -- {:?}\n",
+            effect.effect.pattern().as_ref()
+        )),
+        CheckStatus::CallerChecked => Some(format!(
+            "// cargo-scan: audited caller-checked -- {:?}\n",
+            effect.effect.pattern().as_ref()
+        )),
+        CheckStatus::Unsafe | CheckStatus::Skipped => None,
+    }
+}
+
+/// Byte offset of the start of `line` (1-indexed) within `src`.
+fn line_start_offset(src: &str, line: usize) -> usize {
+    src.split('\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum()
+}
+
+/// The leading whitespace of `line` (1-indexed) within `src`, so an inserted
+/// comment lines up with the code it's annotating instead of starting in
+/// column 0.
+fn line_indent(src: &str, line: usize) -> &str {
+    let text = src.split('\n').nth(line.saturating_sub(1)).unwrap_or("");
+    &text[..text.len() - text.trim_start().len()]
+}
+
+/// Apply every `Safe`/`CallerChecked` effect's suggestion to its source
+/// file. Suggestions in the same file are applied in reverse byte-offset
+/// order so earlier edits don't invalidate later spans; two edits that
+/// collide on the same insertion point (e.g. two effects on the same
+/// source line) are skipped individually, with a warning, rather than
+/// discarding every other suggestion in the file.
+pub fn apply(effects: &[AnnotatedEffect]) -> Result<()> {
+    let mut by_file: HashMap<PathBuf, Vec<Suggestion>> = HashMap::new();
+    let mut src_cache: HashMap<PathBuf, String> = HashMap::new();
+
+    for effect in effects {
+        let Some(text) = comment_for(effect) else {
+            continue;
+        };
+
+        let loc = effect.effect.call_loc();
+        let mut path = loc.dir().clone();
+        path.push(loc.file());
+
+        let src = match src_cache.get(&path) {
+            Some(src) => src.clone(),
+            None => {
+                let src = fs::read_to_string(&path)?;
+                src_cache.insert(path.clone(), src.clone());
+                src
+            }
+        };
+
+        let line = loc.line();
+        let byte_offset = line_start_offset(&src, line);
+        let text = format!("{}{}", line_indent(&src, line), text);
+        by_file.entry(path).or_default().push(Suggestion {
+            byte_offset,
+            line,
+            text,
+        });
+    }
+
+    for (path, mut suggestions) in by_file {
+        suggestions.sort_by_key(|s| std::cmp::Reverse(s.byte_offset));
+
+        // Two suggestions landing on the same insertion point would corrupt
+        // the file if both were applied (their order is arbitrary and one
+        // would end up inside the other's text), so drop just the colliding
+        // suggestions and keep every other edit in the file.
+        let mut deduped = Vec::with_capacity(suggestions.len());
+        let mut iter = suggestions.into_iter().peekable();
+        while let Some(suggestion) = iter.next() {
+            let collided = iter
+                .peek()
+                .is_some_and(|next| next.byte_offset == suggestion.byte_offset);
+            if collided {
+                let mut lines = vec![suggestion.line];
+                while let Some(next) =
+                    iter.next_if(|next| next.byte_offset == suggestion.byte_offset)
+                {
+                    lines.push(next.line);
+                }
+                warn!(
+                    "{:?}: skipping {} colliding edits at lines {:?} (multiple effects on the \
+                     same line)",
+                    path,
+                    lines.len(),
+                    lines
+                );
+            } else {
+                deduped.push(suggestion);
+            }
+        }
+
+        let mut src = src_cache
+            .remove(&path)
+            .unwrap_or(fs::read_to_string(&path)?);
+        for suggestion in deduped {
+            src.insert_str(suggestion.byte_offset, &suggestion.text);
+        }
+        fs::write(&path, src)?;
+    }
+
+    Ok(())
+}
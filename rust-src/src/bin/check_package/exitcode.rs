@@ -0,0 +1,13 @@
+//! Process exit codes for `check_package`, following the `exitcode` crate's
+//! sysexits-style convention so scripts and CI can branch on distinct
+//! failure classes instead of a single boolean success/failure.
+
+/// Everything checked out: no new or unreviewed effects.
+pub const OK: i32 = 0;
+
+/// The scan succeeded but found effects that aren't accounted for in the
+/// check file (new, or previously marked `Skipped`).
+pub const DATAERR: i32 = 65;
+
+/// Couldn't read/parse the crate, the check file, or a source file.
+pub const IOERR: i32 = 74;
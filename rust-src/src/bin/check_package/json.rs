@@ -0,0 +1,66 @@
+//! JSON Lines diagnostic schema for `check_package --message-format=json`.
+//!
+//! Kept as dedicated serde structs separate from the human codespan-reporting
+//! emitter in the parent module, the same way rustc's json emitter keeps its
+//! `Diagnostic*` structs apart from its human emitter.
+
+use cargo_scan::effect::Effect;
+use serde::Serialize;
+use std::path::Path;
+
+/// Byte and line/column span of an effect within its source file. `col_start`/
+/// `col_end` are `SrcLoc`'s column convention (same as the human view's
+/// underline); `byte_start`/`byte_end` are byte offsets into the file, not
+/// just the enclosing line.
+#[derive(Serialize)]
+pub struct JsonSpan {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub col_start: usize,
+    pub col_end: usize,
+}
+
+/// One effect, ready to be emitted as a single JSON Lines record.
+#[derive(Serialize)]
+pub struct JsonEffect<'a> {
+    pub pattern: String,
+    /// The resolved path to the source file the effect was found in (the
+    /// crate directory joined with `call_loc().file()`), not just the bare
+    /// filename `call_loc` stores -- resolvable on its own, without the
+    /// caller having to know which crate directory to join it against.
+    pub file: String,
+    pub span: JsonSpan,
+    /// Byte range of the surrounding source snippet shown in the human view.
+    pub snippet_span: JsonSpan,
+    /// The effect's current status in the loaded check file, if any.
+    pub check_status: Option<&'a str>,
+}
+
+/// One sink that's transitively reachable across the merged cross-crate call
+/// graph, ready to be emitted as a single JSON Lines record -- see
+/// `cross_crate_propagation` in the parent module.
+#[derive(Serialize)]
+pub struct JsonCrossCratePropagation {
+    pub sink: String,
+    pub callers: Vec<String>,
+}
+
+/// Serialize a single effect as one JSON Lines record (no trailing newline).
+pub fn effect_to_json_line(
+    effect: &Effect,
+    file: &Path,
+    span: JsonSpan,
+    snippet_span: JsonSpan,
+    check_status: Option<&str>,
+) -> serde_json::Result<String> {
+    let record = JsonEffect {
+        pattern: format!("{:?}", effect.pattern().as_ref()),
+        file: file.display().to_string(),
+        span,
+        snippet_span,
+        check_status,
+    };
+    serde_json::to_string(&record)
+}
@@ -3,58 +3,241 @@
     code or FFI calls, printing them to stdout.
 */
 
-use cargo_scan::scanner;
+mod diagnostics;
+mod policy;
+
+use diagnostics::{Diagnostic, Level};
+use policy::UnsafePolicy;
+
+use cargo_scan::effect::BlockType;
+use cargo_scan::scanner::{self, ActiveConfig};
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::path::PathBuf;
 
+/// How to print the scan's findings.
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// The raw `{:?}` dump of each finding (the original, pre-diagnostics
+    /// behavior).
+    Plain,
+    /// A compiler-style message with the surrounding source and a `^^^^`
+    /// underline on the exact span.
+    Annotated,
+    /// One JSON object per line, for CI/dashboard consumption.
+    Json,
+}
+
+/// One finding, ready to be emitted as a single JSON Lines record.
+#[derive(Serialize)]
+struct JsonFinding {
+    kind: &'static str,
+    file: String,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to crate directory; should contain a 'src' directory and a Cargo.toml file
     crate_path: PathBuf,
+
+    /// Number of threads to scan with (defaults to the number of CPUs)
+    #[clap(long = "jobs", short = 'j')]
+    jobs: Option<usize>,
+
+    /// Enabled `#[cfg(feature = "...")]` features; code under a feature not
+    /// listed here is treated as unreachable and skipped
+    #[clap(long = "feature")]
+    features: Vec<String>,
+
+    /// Active `#[cfg(target_os = "...")]` to scan against
+    #[clap(long = "target-os")]
+    target_os: Option<String>,
+
+    /// Active `#[cfg(target_arch = "...")]` to scan against
+    #[clap(long = "target-arch")]
+    target_arch: Option<String>,
+
+    /// Scan `#[cfg(test)]` code as reachable
+    #[clap(long = "cfg-test")]
+    cfg_test: bool,
+
+    /// Also expand macros (via `cargo expand`, falling back to
+    /// `cargo rustc -- -Zunpretty=expanded`) and scan the result, so effects
+    /// hidden inside macro invocations aren't invisible to the audit
+    #[clap(long = "expand-macros")]
+    expand_macros: bool,
+
+    /// Scan every Cargo target (bins, examples, tests, benches, build.rs),
+    /// not just the library, tagging each effect with the target it came from
+    #[clap(long = "all-targets")]
+    all_targets: bool,
+
+    /// How to print the scan's findings
+    #[clap(long = "format", value_enum, default_value_t = OutputFormat::Annotated)]
+    format: OutputFormat,
+
+    /// How to triage unsafe blocks/fns and FFI calls before reporting them
+    #[clap(long = "unsafe-policy", value_enum, default_value_t = UnsafePolicy::AllUnsafe)]
+    unsafe_policy: UnsafePolicy,
+
+    /// Wrapper type path prefixes to trust under `--unsafe-policy
+    /// references-wrapped`, e.g. `std::ptr::NonNull`
+    #[clap(long = "trust-wrapper")]
+    trust_wrapper: Vec<String>,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let results = scanner::scan_crate(&args.crate_path)?;
-
-    if !results.unsafe_blocks.is_empty() {
-        println!("=== Unsafe blocks ===");
-        for bl_decl in results.unsafe_blocks {
-            println!("{:?}", bl_decl);
-        }
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
     }
 
-    if !results.unsafe_decls.is_empty() {
-        println!("=== Unsafe fn declarations ===");
-        for fn_decl in results.unsafe_decls {
-            println!("{:?}", fn_decl);
-        }
-    }
+    let active_cfg = ActiveConfig {
+        features: args.features.into_iter().collect::<HashSet<_>>(),
+        target_os: args.target_os,
+        target_arch: args.target_arch,
+        test: args.cfg_test,
+    };
+
+    let results = if args.all_targets {
+        scanner::scan_crate_all_targets(&args.crate_path, HashSet::new(), active_cfg)?
+    } else if args.expand_macros {
+        scanner::scan_crate_expanded(&args.crate_path, HashSet::new(), active_cfg)?
+    } else {
+        scanner::scan_crate_with_cfg(&args.crate_path, HashSet::new(), active_cfg)?
+    };
+
+    let unsafe_blocks: Vec<_> = results
+        .effect_blocks
+        .iter()
+        .filter(|b| matches!(b.block_type(), BlockType::UnsafeExpr))
+        .filter(|b| args.unsafe_policy.keep(b, &args.trust_wrapper))
+        .collect();
+    let unsafe_decls: Vec<_> = results
+        .effect_blocks
+        .iter()
+        .filter(|b| matches!(b.block_type(), BlockType::UnsafeFn))
+        .filter(|b| args.unsafe_policy.keep(b, &args.trust_wrapper))
+        .collect();
+    let ffi_calls: Vec<_> = results
+        .effects
+        .iter()
+        .filter(|e| e.ffi().is_some())
+        .collect();
 
-    if !results.unsafe_traits.is_empty() {
-        println!("=== Unsafe trait declarations ===");
-        for tr_decl in results.unsafe_traits {
-            println!("{:?}", tr_decl);
+    match args.format {
+        OutputFormat::Plain => {
+            if !unsafe_blocks.is_empty() {
+                println!("=== Unsafe blocks ===");
+                for bl_decl in &unsafe_blocks {
+                    println!("{:?}", bl_decl);
+                }
+            }
+            if !unsafe_decls.is_empty() {
+                println!("=== Unsafe fn declarations ===");
+                for fn_decl in &unsafe_decls {
+                    println!("{:?}", fn_decl);
+                }
+            }
+            if !results.unsafe_traits.is_empty() {
+                println!("=== Unsafe trait declarations ===");
+                for tr_decl in &results.unsafe_traits {
+                    println!("{:?}", tr_decl);
+                }
+            }
+            if !results.unsafe_impls.is_empty() {
+                println!("=== Unsafe trait impls ===");
+                for impl_decl in &results.unsafe_impls {
+                    println!("{:?}", impl_decl);
+                }
+            }
+            if !ffi_calls.is_empty() {
+                println!("=== FFI Calls ===");
+                for ffi_call in &ffi_calls {
+                    println!("{:?}", ffi_call);
+                }
+            }
         }
-    }
+        OutputFormat::Annotated => {
+            let diags = unsafe_blocks
+                .iter()
+                .map(|b| Diagnostic::new(Level::Warning, "unsafe block", b.loc().clone()))
+                .chain(unsafe_decls.iter().map(|b| {
+                    Diagnostic::new(Level::Warning, "unsafe fn declaration", b.loc().clone())
+                }))
+                .chain(results.unsafe_traits.iter().map(|t| {
+                    Diagnostic::new(Level::Warning, "unsafe trait declaration", t.loc().clone())
+                }))
+                .chain(
+                    results.unsafe_impls.iter().map(|i| {
+                        Diagnostic::new(Level::Warning, "unsafe impl", i.loc().clone())
+                    }),
+                )
+                .chain(ffi_calls.iter().map(|e| {
+                    Diagnostic::new(
+                        Level::Note,
+                        format!("FFI call: {:?}", e.callee()),
+                        e.call_loc().clone(),
+                    )
+                }))
+                .collect::<Vec<_>>();
 
-    if !results.unsafe_impls.is_empty() {
-        println!("=== Unsafe trait impls ===");
-        for impl_decl in results.unsafe_impls {
-            println!("{:?}", impl_decl);
+            print!("{}", diagnostics::render_all(&diags));
         }
-    }
+        OutputFormat::Json => {
+            let findings = unsafe_blocks
+                .iter()
+                .map(|b| ("unsafe_block", b.loc(), "unsafe block".to_string()))
+                .chain(unsafe_decls.iter().map(|b| {
+                    (
+                        "unsafe_fn_decl",
+                        b.loc(),
+                        "unsafe fn declaration".to_string(),
+                    )
+                }))
+                .chain(results.unsafe_traits.iter().map(|t| {
+                    (
+                        "unsafe_trait",
+                        t.loc(),
+                        "unsafe trait declaration".to_string(),
+                    )
+                }))
+                .chain(
+                    results
+                        .unsafe_impls
+                        .iter()
+                        .map(|i| ("unsafe_impl", i.loc(), "unsafe impl".to_string())),
+                )
+                .chain(ffi_calls.iter().map(|e| {
+                    (
+                        "ffi_call",
+                        e.call_loc(),
+                        format!("FFI call: {:?}", e.callee()),
+                    )
+                }));
 
-    if !results.ffi_calls.is_empty() {
-        println!("=== FFI Calls ===");
-        for ffi_call in results.ffi_calls {
-            println!("{:?}", ffi_call);
+            for (kind, loc, message) in findings {
+                let finding = JsonFinding {
+                    kind,
+                    file: loc.file().display().to_string(),
+                    line: loc.line(),
+                    column: loc.column(),
+                    message,
+                };
+                println!("{}", serde_json::to_string(&finding)?);
+            }
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
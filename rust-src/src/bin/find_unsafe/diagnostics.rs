@@ -0,0 +1,157 @@
+//! Caret-annotated diagnostic rendering for `find_unsafe`.
+//!
+//! Modeled on the slice/snippet design from the `annotate-snippets` crate:
+//! a diagnostic is a severity + title plus one slice of the offending
+//! source (its line text and the byte range to underline within it), and
+//! rendering turns that into a compiler-style `^^^^`-underlined message.
+//! Kept deliberately small, with no dependency beyond `colored`, the same way `check_package`
+//! keeps its JSON emitter (`json.rs`) apart from its human one.
+
+use cargo_scan::effect::SrcLoc;
+
+use std::fs;
+use std::io::IsTerminal;
+use std::ops::Range;
+
+use colored::Colorize;
+
+/// Severity of a diagnostic, controlling both its label word and its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Note,
+}
+
+impl Level {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Note => "note",
+        }
+    }
+}
+
+/// One finding to render: a severity, a one-line title, and the source
+/// location it points at.
+pub struct Diagnostic {
+    pub level: Level,
+    pub title: String,
+    pub loc: SrcLoc,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, title: impl Into<String>, loc: SrcLoc) -> Self {
+        Self {
+            level,
+            title: title.into(),
+            loc,
+        }
+    }
+}
+
+/// A single line of source, the span within it to underline, and the
+/// `file:line:col` header to print above it.
+struct Slice {
+    origin: String,
+    line_number: usize,
+    line_text: String,
+    underline: Range<usize>,
+}
+
+/// Load the diagnostic's source file and carve out the line + underline
+/// span it points at, underlining the identifier at the reported column
+/// (a single character if the column doesn't land on one).
+fn slice_for(loc: &SrcLoc) -> std::io::Result<Slice> {
+    let mut path = loc.dir().clone();
+    path.push(loc.file());
+    let src = fs::read_to_string(path)?;
+
+    let line_text = src
+        .lines()
+        .nth(loc.line().saturating_sub(1))
+        .unwrap_or("")
+        .to_string();
+
+    let start = loc.column().saturating_sub(1).min(line_text.len());
+    let len = line_text[start..]
+        .find(|c: char| !c.is_alphanumeric() && c != '_' && c != ':')
+        .unwrap_or(line_text.len() - start)
+        .max(1);
+    let underline = start..(start + len).min(line_text.len());
+
+    let origin = format!("{}:{}:{}", loc.file().display(), loc.line(), loc.column());
+    Ok(Slice {
+        origin,
+        line_number: loc.line(),
+        line_text,
+        underline,
+    })
+}
+
+/// Render one diagnostic: a `severity: title` header, a `-->` location
+/// line, the source line, and a `^^^^` underline at the exact span.
+/// Colored when `color` is set (callers should gate this on the output
+/// stream being a TTY).
+pub fn render(diag: &Diagnostic, color: bool) -> String {
+    let slice = match slice_for(&diag.loc) {
+        Ok(slice) => slice,
+        Err(err) => {
+            return format!(
+                "{}: {} ({}: {})",
+                diag.level.label(),
+                diag.title,
+                diag.loc,
+                err
+            )
+        }
+    };
+
+    let gutter = " ".repeat(slice.line_number.to_string().len());
+    let underline: String = std::iter::repeat(' ')
+        .take(slice.underline.start)
+        .chain(std::iter::repeat('^').take(slice.underline.len()))
+        .collect();
+
+    if color {
+        let label = match diag.level {
+            Level::Warning => diag.level.label().yellow().bold(),
+            Level::Note => diag.level.label().blue().bold(),
+        };
+        format!(
+            "{}: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}\n",
+            label,
+            diag.title.bold(),
+            gutter,
+            slice.origin,
+            gutter,
+            slice.line_number,
+            slice.line_text,
+            gutter,
+            underline.red().bold()
+        )
+    } else {
+        format!(
+            "{}: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}\n",
+            diag.level.label(),
+            diag.title,
+            gutter,
+            slice.origin,
+            gutter,
+            slice.line_number,
+            slice.line_text,
+            gutter,
+            underline
+        )
+    }
+}
+
+/// Render a full list of diagnostics to a single string, colored iff
+/// stdout is a TTY.
+pub fn render_all(diags: &[Diagnostic]) -> String {
+    let color = std::io::stdout().is_terminal();
+    diags
+        .iter()
+        .map(|d| render(d, color))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,53 @@
+//! Unsafe/FFI triage policy for `find_unsafe`.
+//!
+//! Modeled on autocxx-parser's `UnsafePolicy`: rather than always reporting
+//! every unsafe surface the scanner found, a policy decides which ones are
+//! actually worth a human's attention.
+
+use cargo_scan::effect::EffectBlock;
+
+use clap::ValueEnum;
+
+/// How to treat unsafe/FFI surfaces when reporting findings.
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+pub enum UnsafePolicy {
+    /// Report every unsafe block, unsafe fn, and FFI call (the original,
+    /// pre-policy behavior).
+    AllUnsafe,
+    /// Suppress unsafe blocks/fns that don't contain a foreign call --
+    /// i.e. only flag unsafe surfaces that actually cross the FFI boundary.
+    AllSafeExceptFfi,
+    /// Treat an `unsafe fn`/block as effectively safe when every unsafe
+    /// operation in its body goes through one of the caller-supplied
+    /// trusted wrapper types (`--trust-wrapper`).
+    ReferencesWrapped,
+}
+
+impl UnsafePolicy {
+    /// Whether `block` should still be reported as a finding under this
+    /// policy, given the caller-supplied list of trusted wrapper type
+    /// path prefixes (only consulted under `ReferencesWrapped`).
+    pub fn keep(&self, block: &EffectBlock, trusted_wrappers: &[String]) -> bool {
+        match self {
+            Self::AllUnsafe => true,
+            Self::AllSafeExceptFfi => block.effects().iter().any(|e| e.ffi().is_some()),
+            Self::ReferencesWrapped => block
+                .effects()
+                .iter()
+                .any(|e| e.ffi().is_some() || !through_trusted_wrapper(e, trusted_wrappers)),
+        }
+    }
+}
+
+/// Whether this effect's callee is reached entirely through one of the
+/// trusted wrapper types, e.g. `--trust-wrapper std::ptr::NonNull` trusts
+/// `std::ptr::NonNull::as_ref`.
+fn through_trusted_wrapper(
+    effect: &cargo_scan::effect::EffectInstance,
+    trusted_wrappers: &[String],
+) -> bool {
+    let callee = effect.callee().to_string();
+    trusted_wrappers
+        .iter()
+        .any(|wrapper| callee.starts_with(wrapper.as_str()))
+}
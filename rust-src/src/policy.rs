@@ -5,6 +5,8 @@
     See example .policy files in policies/
 */
 
+mod dsl;
+
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
@@ -13,13 +15,35 @@ use std::fmt::{self, Display};
 use std::path::Path;
 
 use super::ident::{FnCall, Path as IdentPath};
+use super::util::SpdxExpression;
+
+pub use dsl::ParseError;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Statement {
-    Allow { region: FnCall, effect: FnCall },
-    Require { region: FnCall, effect: FnCall },
-    Trust { region: FnCall },
+    Allow {
+        region: FnCall,
+        effect: FnCall,
+    },
+    Require {
+        region: FnCall,
+        effect: FnCall,
+    },
+    Trust {
+        region: FnCall,
+    },
+    /// The scanned crate's (or, once dependency scanning supports it, one
+    /// of its transitive deps') SPDX license expression must be
+    /// OR-compatible with `license`, e.g. `MIT OR Apache-2.0`.
+    RequireLicense {
+        license: String,
+    },
+    /// The scanned crate's SPDX license expression must not match
+    /// `license`, e.g. `GPL-3.0-only`.
+    DenyLicense {
+        license: String,
+    },
 }
 impl Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -33,6 +57,12 @@ impl Display for Statement {
             Self::Trust { region } => {
                 write!(f, "trust {}", region)
             }
+            Self::RequireLicense { license } => {
+                write!(f, "require-license {}", license)
+            }
+            Self::DenyLicense { license } => {
+                write!(f, "deny-license {}", license)
+            }
         }
     }
 }
@@ -59,6 +89,16 @@ impl Statement {
         let region = FnCall::new_all(path);
         Self::Trust { region }
     }
+    pub fn require_license(license: &str) -> Self {
+        Self::RequireLicense {
+            license: license.to_string(),
+        }
+    }
+    pub fn deny_license(license: &str) -> Self {
+        Self::DenyLicense {
+            license: license.to_string(),
+        }
+    }
 }
 
 // TODO: make crate_version and policy_version semver objects
@@ -75,13 +115,27 @@ impl Policy {
         let crate_version = crate_version.to_string();
         let policy_version = policy_version.to_string();
         let statements = Vec::new();
-        Policy { crate_name, crate_version, policy_version, statements }
+        Policy {
+            crate_name,
+            crate_version,
+            policy_version,
+            statements,
+        }
     }
     pub fn from_file(file: &Path) -> Result<Self, Box<dyn Error>> {
-        debug_assert_eq!(file.extension(), Some(OsStr::new("toml")));
-        let toml_str = std::fs::read_to_string(file)?;
-        let policy: Policy = toml::from_str(&toml_str)?;
-        Ok(policy)
+        match file.extension() {
+            Some(ext) if ext == OsStr::new("policy") => {
+                let policy_str = std::fs::read_to_string(file)?;
+                let policy = dsl::parse(&policy_str)?;
+                Ok(policy)
+            }
+            _ => {
+                debug_assert_eq!(file.extension(), Some(OsStr::new("toml")));
+                let toml_str = std::fs::read_to_string(file)?;
+                let policy: Policy = toml::from_str(&toml_str)?;
+                Ok(policy)
+            }
+        }
     }
     pub fn add_statement(&mut self, s: Statement) {
         self.statements.push(s);
@@ -101,6 +155,27 @@ impl Policy {
     pub fn trust(&mut self, path: &str) {
         self.add_statement(Statement::trust(path))
     }
+    pub fn require_license(&mut self, license: &str) {
+        self.add_statement(Statement::require_license(license))
+    }
+    pub fn deny_license(&mut self, license: &str) {
+        self.add_statement(Statement::deny_license(license))
+    }
+}
+impl Display for Policy {
+    /// Render back into the `.policy` DSL: a `crate` header line followed
+    /// by one statement per line, parseable by [`dsl::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "crate {} {} {}",
+            self.crate_name, self.crate_version, self.policy_version
+        )?;
+        for stmt in &self.statements {
+            writeln!(f, "{}", stmt)?;
+        }
+        Ok(())
+    }
 }
 
 /// Quick-lookup summary of the policy.
@@ -111,11 +186,28 @@ impl Policy {
 pub struct PolicyLookup {
     allow_sets: HashMap<IdentPath, HashSet<IdentPath>>,
     require_sets: HashMap<IdentPath, HashSet<IdentPath>>,
+    /// Trusted regions, SELinux-style: a path in this set covers itself and
+    /// every descendant path (anything prefixed `<path>::`). A call whose
+    /// caller lies in a trusted region needs no allow entry for the
+    /// effects it reaches.
+    trust_set: HashSet<IdentPath>,
+    /// Raw `require-license` expressions; a scanned crate's license must be
+    /// OR-compatible with each of these.
+    license_requirements: Vec<String>,
+    /// Raw `deny-license` expressions; a scanned crate's license must not
+    /// match any of these.
+    license_denials: Vec<String>,
 }
 #[allow(dead_code, unused_variables)]
 impl PolicyLookup {
     pub fn empty() -> Self {
-        Self { allow_sets: HashMap::new(), require_sets: HashMap::new() }
+        Self {
+            allow_sets: HashMap::new(),
+            require_sets: HashMap::new(),
+            trust_set: HashSet::new(),
+            license_requirements: Vec::new(),
+            license_denials: Vec::new(),
+        }
     }
     pub fn from_policy(p: &Policy) -> Self {
         let mut result = Self::empty();
@@ -126,12 +218,18 @@ impl PolicyLookup {
     }
     pub fn add_statement(&mut self, stmt: &Statement) {
         match stmt {
-            Statement::Allow { region: r, effect: e } => {
+            Statement::Allow {
+                region: r,
+                effect: e,
+            } => {
                 let caller = r.fn_path().clone();
                 let eff = e.fn_path().clone();
                 self.allow_sets.entry(caller).or_default().insert(eff);
             }
-            Statement::Require { region: r, effect: e } => {
+            Statement::Require {
+                region: r,
+                effect: e,
+            } => {
                 let caller = r.fn_path().clone();
                 let eff = e.fn_path().clone();
                 self.require_sets.entry(caller).or_default().insert(eff);
@@ -140,17 +238,36 @@ impl PolicyLookup {
                 let eff = e.fn_path().clone();
                 self.allow_sets.entry(caller).or_default().insert(eff);
             }
-            Statement::Trust { region: _ } => {
-                unimplemented!()
+            Statement::Trust { region: r } => {
+                self.trust_set.insert(r.fn_path().clone());
+            }
+            Statement::RequireLicense { license } => {
+                self.license_requirements.push(license.clone());
+            }
+            Statement::DenyLicense { license } => {
+                self.license_denials.push(license.clone());
             }
         }
     }
+
+    /// Whether `caller` lies inside any trusted region, i.e. it equals or
+    /// is a descendant of some trusted path.
+    fn is_trusted(&self, caller: &IdentPath) -> bool {
+        let caller = caller.to_string();
+        self.trust_set.iter().any(|trusted| {
+            let trusted = trusted.to_string();
+            caller == trusted || caller.starts_with(&format!("{}::", trusted))
+        })
+    }
     /// Mark a fn call is an interesting/dangerous call.
     /// This must be done before any check_edge invocations.
     ///
     /// We re-use the require list for this, since it serves the same purpose!
     pub fn mark_of_interest(&mut self, callee: &IdentPath) {
-        self.require_sets.entry(callee.clone()).or_default().insert(callee.clone());
+        self.require_sets
+            .entry(callee.clone())
+            .or_default()
+            .insert(callee.clone());
     }
 
     // internal function for check_edge
@@ -169,16 +286,19 @@ impl PolicyLookup {
                 ))
             }
         } else {
-            Err(format!("No allow list for function {} with effect {}", caller, effect))
+            Err(format!(
+                "No allow list for function {} with effect {}",
+                caller, effect
+            ))
         }
     }
 
     /// Iterate over effects required at a particular path
-    pub fn iter_requirements(
-        &self,
-        callee: &IdentPath,
-    ) -> impl Iterator<Item = &IdentPath> {
-        self.require_sets.get(callee).into_iter().flat_map(|require| require.iter())
+    pub fn iter_requirements(&self, callee: &IdentPath) -> impl Iterator<Item = &IdentPath> {
+        self.require_sets
+            .get(callee)
+            .into_iter()
+            .flat_map(|require| require.iter())
     }
 
     /// Check a call graph edge against the policy.
@@ -190,6 +310,9 @@ impl PolicyLookup {
         callee: &IdentPath,
         error_list: &mut Vec<String>,
     ) {
+        if self.is_trusted(caller) {
+            return;
+        }
         for req in self.iter_requirements(callee) {
             self.allow_list_contains(caller, req).unwrap_or_else(|err| {
                 error_list.push(err);
@@ -201,6 +324,9 @@ impl PolicyLookup {
     /// Rather than returning a list of errors, just return a Boolean
     /// of whether it passes or not.
     pub fn check_edge_bool(&self, caller: &IdentPath, callee: &IdentPath) -> bool {
+        if self.is_trusted(caller) {
+            return true;
+        }
         for req in self.iter_requirements(callee) {
             if self.allow_list_contains(caller, req).is_err() {
                 return false;
@@ -208,6 +334,141 @@ impl PolicyLookup {
         }
         true
     }
+
+    /// Validate a whole call graph (as an iterator of caller -> callee
+    /// edges) against this policy, rather than one edge at a time.
+    ///
+    /// `check_edge`/`check_edge_bool` only see a single hop: if `f` calls
+    /// `g` and `g` calls the effect directly, the edge `f -> g` doesn't
+    /// know that `g` itself goes on to exercise an effect of interest two
+    /// hops further down, so a requirement on `g`'s callee never gets
+    /// checked against `f`'s allow list. This computes, via a worklist
+    /// fixpoint, the transitive effect set each function exercises:
+    /// starting from `require_sets` (including the `mark_of_interest`
+    /// entries), a callee's effect set is pulled into every caller's set,
+    /// except for effects the caller's `allow_sets` already covers --
+    /// allow is a cut point that stops that effect from propagating any
+    /// further up the graph. Iteration continues until no function's
+    /// effect set changes.
+    ///
+    /// Sets only grow and are bounded by the number of distinct effects,
+    /// so the fixpoint always terminates, cycles included: this resolves
+    /// the open question in `test_policy_lookup_cycle`, where a
+    /// `require`-only cycle with no allow statements can propagate an
+    /// effect around the cycle indefinitely in principle but still
+    /// converges in practice, since there's nothing new left to add after
+    /// at most one full trip around. To stop a requirement from bubbling
+    /// all the way up to an entrypoint that legitimately performs it
+    /// (e.g. `main`), `trust`-mark that entrypoint: a trusted caller never
+    /// picks up its callees' effects, so nothing propagates past it, and it
+    /// is exempted from every check below, the same as in `check_edge`.
+    ///
+    /// Returns one error string per violating edge/effect pair, same
+    /// format as `check_edge`.
+    pub fn verify_graph<'a>(
+        &self,
+        edges: impl IntoIterator<Item = (&'a IdentPath, &'a IdentPath)>,
+    ) -> Vec<String> {
+        let edges: Vec<(&IdentPath, &IdentPath)> = edges.into_iter().collect();
+
+        let mut effect_sets: HashMap<IdentPath, HashSet<IdentPath>> = self.require_sets.clone();
+
+        loop {
+            let mut changed = false;
+            for &(caller, callee) in &edges {
+                if self.is_trusted(caller) {
+                    continue;
+                }
+                let Some(callee_effects) = effect_sets.get(callee).cloned() else {
+                    continue;
+                };
+                let allowed = self.allow_sets.get(caller);
+                let caller_set = effect_sets.entry(caller.clone()).or_default();
+                for eff in callee_effects {
+                    if allowed.map_or(false, |allow| allow.contains(&eff)) {
+                        continue;
+                    }
+                    if caller_set.insert(eff) {
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut error_list = Vec::new();
+        for (caller, callee) in edges {
+            if self.is_trusted(caller) {
+                continue;
+            }
+            if let Some(effects) = effect_sets.get(callee) {
+                for eff in effects {
+                    self.allow_list_contains(caller, eff).unwrap_or_else(|err| {
+                        error_list.push(err);
+                    });
+                }
+            }
+        }
+        error_list
+    }
+
+    /// Check a scanned crate's SPDX license expression against every
+    /// `require-license`/`deny-license` statement, returning one error
+    /// string per violated statement (same style as `check_edge`).
+    ///
+    /// A `require-license` is satisfied if `license` shares at least one
+    /// OR-branch with the required expression (so e.g. `MIT OR GPL-3.0`
+    /// satisfies a `require-license MIT OR Apache-2.0` policy via the MIT
+    /// branch); a `deny-license` is violated under the same test.
+    pub fn check_license(&self, license: &SpdxExpression) -> Vec<String> {
+        let mut error_list = Vec::new();
+        for required in &self.license_requirements {
+            match SpdxExpression::parse(required) {
+                Ok(required_expr) => {
+                    if !licenses_overlap(license, &required_expr) {
+                        error_list.push(format!(
+                            "License `{}` does not satisfy required license `{}`",
+                            license, required
+                        ));
+                    }
+                }
+                Err(err) => error_list.push(format!(
+                    "Invalid license expression `{}` in policy: {}",
+                    required, err
+                )),
+            }
+        }
+        for denied in &self.license_denials {
+            match SpdxExpression::parse(denied) {
+                Ok(denied_expr) => {
+                    if licenses_overlap(license, &denied_expr) {
+                        error_list.push(format!(
+                            "License `{}` matches denied license `{}`",
+                            license, denied
+                        ));
+                    }
+                }
+                Err(err) => error_list.push(format!(
+                    "Invalid license expression `{}` in policy: {}",
+                    denied, err
+                )),
+            }
+        }
+        error_list
+    }
+}
+
+/// Whether `license` can be satisfied using only license ids that appear in
+/// `other`, i.e. at least one of `license`'s OR-branches is made up entirely
+/// of ids `other` mentions (so `MIT AND Apache-2.0` does not overlap `MIT`).
+fn licenses_overlap(license: &SpdxExpression, other: &SpdxExpression) -> bool {
+    let other_ids: HashSet<_> = other
+        .requirements()
+        .filter_map(|er| er.req.license.id())
+        .collect();
+    license.evaluate(|req| req.license.id().map_or(false, |id| other_ids.contains(&id)))
 }
 
 #[cfg(test)]
@@ -325,6 +586,46 @@ mod tests {
         assert!(lookup.check_edge_bool(&foo, &bar));
     }
 
+    #[test]
+    fn test_policy_lookup_trust() {
+        let mut policy = ex_policy();
+        policy.trust("foo");
+        let lookup = ex_lookup(&policy);
+
+        let foo = IdentPath("foo".to_string());
+        let foo_bar = IdentPath("foo::bar".to_string());
+        let baz = IdentPath("baz".to_string());
+        let eff1 = IdentPath("std::effect".to_string());
+        let eff2 = IdentPath("libc::effect".to_string());
+
+        println!("{:?}", policy);
+        println!("{:?}", lookup);
+
+        // foo itself, and any descendant path such as foo::bar, is inside
+        // the trusted region and needs no allow entry to reach an effect
+        assert!(lookup.check_edge_bool(&foo, &eff1));
+        assert!(lookup.check_edge_bool(&foo_bar, &eff1));
+        assert!(lookup.check_edge_bool(&foo_bar, &eff2));
+
+        // callers outside the trusted prefix still fail as usual
+        assert!(!lookup.check_edge_bool(&baz, &eff1));
+    }
+
+    #[test]
+    fn test_policy_lookup_trust_prefix_boundary() {
+        let mut policy = ex_policy();
+        policy.trust("foo");
+        let lookup = ex_lookup(&policy);
+
+        // "foobar" merely shares a string prefix with "foo"; it isn't a
+        // descendant path segment (that would be "foo::bar"), so it must
+        // not be treated as trusted
+        let foobar = IdentPath("foobar".to_string());
+        let eff1 = IdentPath("std::effect".to_string());
+
+        assert!(!lookup.check_edge_bool(&foobar, &eff1));
+    }
+
     #[test]
     fn test_policy_lookup_1() {
         let mut policy = ex_policy();
@@ -396,6 +697,96 @@ mod tests {
         assert!(lookup.check_edge_bool(&bar, &foo));
     }
 
+    #[test]
+    fn test_policy_verify_graph_transitive() {
+        // f1 -> f2 -> effect, with no requirement or allow recorded at
+        // f2 itself: a bare single-hop check_edge_bool(f1, f2) can't see
+        // that f2 transitively reaches the effect two hops down, so it
+        // wrongly passes -- verify_graph's fixpoint propagates the
+        // effect up through f2 to f1 and catches it on both edges.
+        let policy = ex_policy();
+        let lookup = ex_lookup(&policy);
+
+        let f1 = IdentPath::new("f1");
+        let f2 = IdentPath::new("f2");
+        let eff = IdentPath::new("libc::effect");
+
+        // single-hop check doesn't see past f2
+        assert!(lookup.check_edge_bool(&f1, &f2));
+
+        let edges = vec![(&f1, &f2), (&f2, &eff)];
+        let errors = lookup.verify_graph(edges);
+        assert_eq!(errors.len(), 2);
+
+        // allowing the effect at both f1 and f2 closes the gap
+        let mut policy = ex_policy();
+        policy.allow_simple("f1", "libc::effect");
+        policy.allow_simple("f2", "libc::effect");
+        let lookup = ex_lookup(&policy);
+        let errors = lookup.verify_graph(vec![(&f1, &f2), (&f2, &eff)]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_policy_verify_graph_allow_is_cut_point() {
+        // f1 -> f2 -> f3 -> effect, with only f2 allowed to reach the
+        // effect: the allow at f2 absorbs the propagated requirement and
+        // stops it there, so f1 needs no allow entry of its own -- but
+        // f3 still needs (and lacks) its own allow for the direct call.
+        let mut policy = ex_policy();
+        policy.allow_simple("f2", "libc::effect");
+        let lookup = ex_lookup(&policy);
+
+        let f1 = IdentPath::new("f1");
+        let f2 = IdentPath::new("f2");
+        let f3 = IdentPath::new("f3");
+        let eff = IdentPath::new("libc::effect");
+
+        let edges = vec![(&f1, &f2), (&f2, &f3), (&f3, &eff)];
+        let errors = lookup.verify_graph(edges);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_policy_verify_graph_cycle_terminates() {
+        // foo and bar call each other, and bar also calls the effect
+        // directly. The fixpoint must still converge even though the
+        // effect can flow around the foo <-> bar cycle: it propagates once around the
+        // cycle and then stabilizes, since there's nothing new left to
+        // add. With no allow/trust statements anywhere, every edge is a
+        // violation.
+        let policy = ex_policy();
+        let lookup = ex_lookup(&policy);
+
+        let foo = IdentPath::new("foo");
+        let bar = IdentPath::new("bar");
+        let eff = IdentPath::new("libc::effect");
+
+        let edges = vec![(&foo, &bar), (&bar, &foo), (&bar, &eff)];
+        let errors = lookup.verify_graph(edges);
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_policy_verify_graph_trust_cuts_cycle() {
+        // Trust-marking both functions in the cycle as an entrypoint
+        // region stops the effect from propagating past either of them,
+        // resolving the open question from test_policy_lookup_cycle: a
+        // require-only cycle is fine once its entrypoints are trusted.
+        let mut policy = ex_policy();
+        policy.trust("foo");
+        policy.trust("bar");
+        let lookup = ex_lookup(&policy);
+
+        let foo = IdentPath::new("foo");
+        let bar = IdentPath::new("bar");
+        let eff = IdentPath::new("libc::effect");
+
+        let edges = vec![(&foo, &bar), (&bar, &foo), (&bar, &eff)];
+        let errors = lookup.verify_graph(edges);
+        assert!(errors.is_empty());
+    }
+
     #[test]
     fn test_policy_from_file() {
         let policy_file = Path::new("../policies/permissions-ex.toml");
@@ -422,4 +813,116 @@ mod tests {
 
         assert_eq!(policy1, policy2);
     }
+
+    #[test]
+    fn test_policy_dsl_parse() {
+        let text = "\
+            crate permissions-ex 0.1 0.1\n\
+            # comment lines and blank lines are ignored\n\
+            \n\
+            require permissions-ex::remove(path) fs::delete(path)\n\
+            require permissions-ex::save_data(path) fs::create(path)\n\
+            require permissions-ex::save_data(path) fs::write(path)\n\
+            allow permissions-ex::remove(path) process::exec(rm -f path)\n\
+            allow permissions-ex::save_data(path) fs::delete(path)\n\
+            allow permissions-ex::prepare_data fs::append(my_app.log)\n\
+        ";
+        let policy1 = dsl::parse(text).unwrap();
+
+        let mut policy2 = Policy::new("permissions-ex", "0.1", "0.1");
+        let eff1 = FnCall::new("fs::delete", "path");
+        policy2.require("permissions-ex::remove", "path", eff1);
+        let eff2 = FnCall::new("fs::create", "path");
+        policy2.require("permissions-ex::save_data", "path", eff2);
+        let eff3 = FnCall::new("fs::write", "path");
+        policy2.require("permissions-ex::save_data", "path", eff3);
+        let eff4 = FnCall::new("process::exec", "rm -f path");
+        policy2.allow("permissions-ex::remove", "path", eff4);
+        let eff5 = FnCall::new("fs::delete", "path");
+        policy2.allow("permissions-ex::save_data", "path", eff5);
+        let eff6 = FnCall::new("fs::append", "my_app.log");
+        policy2.allow("permissions-ex::prepare_data", "", eff6);
+
+        assert_eq!(policy1, policy2);
+    }
+
+    #[test]
+    fn test_policy_dsl_round_trip() {
+        let mut policy = Policy::new("permissions-ex", "0.1", "0.1");
+        let eff1 = FnCall::new("fs::delete", "path");
+        policy.require("permissions-ex::remove", "path", eff1);
+        policy.allow_simple("permissions-ex::prepare_data", "fs::append");
+        policy.trust("permissions-ex::trusted_mod");
+        policy.require_license("MIT OR Apache-2.0");
+        policy.deny_license("GPL-3.0-only");
+
+        let printed = policy.to_string();
+        let reparsed = dsl::parse(&printed).unwrap();
+        assert_eq!(policy, reparsed);
+        assert_eq!(printed, reparsed.to_string());
+    }
+
+    #[test]
+    fn test_policy_dsl_parse_license_statements() {
+        let text = "\
+            crate permissions-ex 0.1 0.1\n\
+            require-license MIT OR Apache-2.0\n\
+            deny-license GPL-3.0-only\n\
+        ";
+        let policy1 = dsl::parse(text).unwrap();
+
+        let mut policy2 = Policy::new("permissions-ex", "0.1", "0.1");
+        policy2.require_license("MIT OR Apache-2.0");
+        policy2.deny_license("GPL-3.0-only");
+
+        assert_eq!(policy1, policy2);
+    }
+
+    #[test]
+    fn test_policy_dsl_parse_errors() {
+        let missing_header = "allow foo bar\n";
+        let err = dsl::parse(missing_header).unwrap_err();
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let bad_header = "crate foo 0.1\n";
+        let err = dsl::parse(bad_header).unwrap_err();
+        assert_eq!(err.line, 1);
+
+        let unknown_statement = "crate foo 0.1 0.1\nfoo bar baz\n";
+        let err = dsl::parse(unknown_statement).unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let unterminated_args = "crate foo 0.1 0.1\nallow foo(bar baz\n";
+        let err = dsl::parse(unterminated_args).unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let missing_license = "crate foo 0.1 0.1\nrequire-license\n";
+        let err = dsl::parse(missing_license).unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn test_policy_lookup_license() {
+        let mut policy = ex_policy();
+        policy.require_license("MIT OR Apache-2.0");
+        policy.deny_license("GPL-3.0-only");
+        let lookup = PolicyLookup::from_policy(&policy);
+
+        let mit = SpdxExpression::parse("MIT").unwrap();
+        let gpl = SpdxExpression::parse("GPL-3.0-only").unwrap();
+        let mit_or_gpl = SpdxExpression::parse("MIT OR GPL-3.0-only").unwrap();
+
+        // satisfies the requirement via the MIT branch, doesn't match the denial
+        assert!(lookup.check_license(&mit).is_empty());
+
+        // fails the requirement (no overlap with MIT OR Apache-2.0) and
+        // matches the denial
+        let errors = lookup.check_license(&gpl);
+        assert_eq!(errors.len(), 2);
+
+        // an OR expression satisfies the requirement via its MIT branch,
+        // but still matches the denial via its GPL branch
+        let errors = lookup.check_license(&mit_or_gpl);
+        assert_eq!(errors.len(), 1);
+    }
 }
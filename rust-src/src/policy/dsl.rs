@@ -0,0 +1,296 @@
+//! Parser for the line-oriented `.policy` text DSL.
+//!
+//! This is the hand-written counterpart to `Statement`'s `Display` impl in
+//! the parent module: it accepts exactly the syntax that impl emits, so a
+//! policy can be parsed, printed, and re-parsed without drift. Grammar, one
+//! production per line (blank lines and `#` comments are skipped):
+//!
+//! ```text
+//! policy     := header statement*
+//! header     := "crate" NAME VERSION POLICY_VERSION
+//! statement  := "allow" callable callable
+//!             | "require" callable callable
+//!             | "trust" callable
+//!             | "require-license" LICENSE_EXPR
+//!             | "deny-license" LICENSE_EXPR
+//! callable   := PATH ["(" ARGS ")"]
+//! ```
+//!
+//! `PATH`, `NAME`, `VERSION`, and `POLICY_VERSION` are bare
+//! whitespace-delimited tokens. `ARGS` is the raw text between a callable's
+//! parentheses (nesting isn't supported); a callable with no `(...)`
+//! defaults to `FnCall::new_all`, matching any arguments. `LICENSE_EXPR` is
+//! the rest of the line verbatim (an SPDX expression like `MIT OR
+//! Apache-2.0` is itself whitespace-separated, so it can't be a single
+//! token).
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use super::{Policy, Statement};
+use crate::ident::FnCall;
+
+/// A parse failure, pointing at the 1-based line/column of the offending
+/// token so a caller can report it the way a compiler would.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a full `.policy` file from its text contents.
+pub fn parse(input: &str) -> Result<Policy, ParseError> {
+    let mut lines = input.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    let (crate_name, crate_version, policy_version) = loop {
+        match lines.next() {
+            Some((_, line)) if is_blank_or_comment(line) => continue,
+            Some((lineno, line)) => break parse_header(lineno, line)?,
+            None => {
+                return Err(ParseError {
+                    line: 1,
+                    column: 1,
+                    message: "expected a `crate <name> <version> <policy_version>` header"
+                        .to_string(),
+                })
+            }
+        }
+    };
+
+    let mut policy = Policy::new(&crate_name, &crate_version, &policy_version);
+
+    for (lineno, line) in lines {
+        if is_blank_or_comment(line) {
+            continue;
+        }
+        policy.add_statement(parse_statement(lineno, line)?);
+    }
+
+    Ok(policy)
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn parse_header(lineno: usize, line: &str) -> Result<(String, String, String), ParseError> {
+    let mut cur = Cursor::new(line);
+
+    let column = cur.column();
+    let keyword = cur.take_token().ok_or_else(|| ParseError {
+        line: lineno,
+        column,
+        message: "expected a `crate <name> <version> <policy_version>` header".to_string(),
+    })?;
+    if keyword != "crate" {
+        return Err(ParseError {
+            line: lineno,
+            column,
+            message: format!("expected `crate` header, found `{}`", keyword),
+        });
+    }
+
+    let name = cur.take_token().ok_or_else(|| ParseError {
+        line: lineno,
+        column: cur.column(),
+        message: "expected a crate name after `crate`".to_string(),
+    })?;
+    let version = cur.take_token().ok_or_else(|| ParseError {
+        line: lineno,
+        column: cur.column(),
+        message: "expected a crate version".to_string(),
+    })?;
+    let policy_version = cur.take_token().ok_or_else(|| ParseError {
+        line: lineno,
+        column: cur.column(),
+        message: "expected a policy version".to_string(),
+    })?;
+
+    cur.expect_end(lineno)?;
+    Ok((
+        name.to_string(),
+        version.to_string(),
+        policy_version.to_string(),
+    ))
+}
+
+fn parse_statement(lineno: usize, line: &str) -> Result<Statement, ParseError> {
+    let mut cur = Cursor::new(line);
+
+    let column = cur.column();
+    let keyword = cur.take_token().ok_or_else(|| ParseError {
+        line: lineno,
+        column,
+        message: "expected `allow`, `require`, `trust`, `require-license`, or `deny-license`"
+            .to_string(),
+    })?;
+
+    let stmt = match keyword {
+        "allow" => {
+            let region = parse_callable(lineno, &mut cur)?;
+            let effect = parse_callable(lineno, &mut cur)?;
+            Statement::Allow { region, effect }
+        }
+        "require" => {
+            let region = parse_callable(lineno, &mut cur)?;
+            let effect = parse_callable(lineno, &mut cur)?;
+            Statement::Require { region, effect }
+        }
+        "trust" => {
+            let region = parse_callable(lineno, &mut cur)?;
+            Statement::Trust { region }
+        }
+        "require-license" => Statement::RequireLicense {
+            license: parse_license_expr(lineno, &mut cur)?,
+        },
+        "deny-license" => Statement::DenyLicense {
+            license: parse_license_expr(lineno, &mut cur)?,
+        },
+        other => {
+            return Err(ParseError {
+                line: lineno,
+                column,
+                message: format!(
+                    "unknown statement `{}`, expected `allow`, `require`, `trust`, \
+                     `require-license`, or `deny-license`",
+                    other
+                ),
+            })
+        }
+    };
+
+    cur.expect_end(lineno)?;
+    Ok(stmt)
+}
+
+/// Parse the rest of a `require-license`/`deny-license` line as a raw SPDX
+/// expression string; actual SPDX validation happens lazily when the
+/// statement is checked against a crate's license (see
+/// `PolicyLookup::check_license`), the same way a `.policy` callable isn't
+/// resolved against real effects until lookup time.
+fn parse_license_expr(lineno: usize, cur: &mut Cursor) -> Result<String, ParseError> {
+    let column = cur.column();
+    let expr = cur.take_rest();
+    if expr.is_empty() {
+        return Err(ParseError {
+            line: lineno,
+            column,
+            message: "expected a license expression".to_string(),
+        });
+    }
+    Ok(expr.to_string())
+}
+
+fn parse_callable(lineno: usize, cur: &mut Cursor) -> Result<FnCall, ParseError> {
+    let column = cur.column();
+    let path = cur.take_token().ok_or_else(|| ParseError {
+        line: lineno,
+        column,
+        message: "expected a path".to_string(),
+    })?;
+    let args = cur.take_args().map_err(|message| ParseError {
+        line: lineno,
+        column,
+        message,
+    })?;
+    Ok(match args {
+        Some(args) => FnCall::new(path, args),
+        None => FnCall::new_all(path),
+    })
+}
+
+/// A byte-offset cursor over a single line, used to hand out 1-based
+/// line/column positions for error reporting.
+struct Cursor<'a> {
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Cursor { line, pos: 0 }
+    }
+
+    fn column(&self) -> usize {
+        self.line[..self.pos].chars().count() + 1
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.line[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Take a bare token: everything up to the next whitespace or `(`.
+    fn take_token(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '(' {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        if self.pos == start {
+            None
+        } else {
+            Some(&self.line[start..self.pos])
+        }
+    }
+
+    /// If the cursor is sitting on `(`, consume up to the matching `)` and
+    /// return the text in between; otherwise leave the cursor untouched.
+    fn take_args(&mut self) -> Result<Option<&'a str>, String> {
+        if self.peek() != Some('(') {
+            return Ok(None);
+        }
+        self.pos += 1;
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == ')' {
+                let args = &self.line[start..self.pos];
+                self.pos += 1;
+                return Ok(Some(args));
+            }
+            self.pos += c.len_utf8();
+        }
+        Err("unterminated `(...)`".to_string())
+    }
+
+    /// Consume and return everything left on the line, trimmed of
+    /// surrounding whitespace.
+    fn take_rest(&mut self) -> &'a str {
+        let rest = self.line[self.pos..].trim();
+        self.pos = self.line.len();
+        rest
+    }
+
+    fn expect_end(&mut self, lineno: usize) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.peek().is_some() {
+            return Err(ParseError {
+                line: lineno,
+                column: self.column(),
+                message: "unexpected trailing text".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -16,18 +16,235 @@ use super::util;
 use anyhow::{anyhow, Result};
 use log::{debug, info, warn};
 use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::Direction;
 use proc_macro2::{TokenStream, TokenTree};
 use quote::ToTokens;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path as FilePath;
+use std::path::PathBuf;
 use syn::spanned::Spanned;
+use toml::value::Table;
+
+/// The active configuration (enabled features, target, `test`) to evaluate
+/// `#[cfg(...)]` predicates against while scanning. `#[cfg_attr(...)]`
+/// attributes are not evaluated.
+///
+/// Items whose predicate is statically false under this configuration are
+/// skipped entirely, rather than reported as effects that can never compile.
+#[derive(Debug, Clone, Default)]
+pub struct ActiveConfig {
+    pub features: HashSet<String>,
+    pub target_os: Option<String>,
+    pub target_arch: Option<String>,
+    pub test: bool,
+}
+
+/// The three-valued result of evaluating a `cfg(...)` predicate: known to
+/// hold, known not to hold, or indeterminate because the predicate depends
+/// on a dimension (a feature, `target_os`, ...) the caller never specified.
+///
+/// `Unknown` is deliberately sticky under negation (`!Unknown == Unknown`):
+/// if we don't know whether `feature = "std"` holds, we also don't know
+/// whether `not(feature = "std")` holds, so both the feature-gated code and
+/// its `#[cfg(not(...))]` fallback are scanned rather than one of them being
+/// silently dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tri {
+    True,
+    False,
+    Unknown,
+}
+
+impl std::ops::Not for Tri {
+    type Output = Tri;
+    fn not(self) -> Tri {
+        match self {
+            Tri::True => Tri::False,
+            Tri::False => Tri::True,
+            Tri::Unknown => Tri::Unknown,
+        }
+    }
+}
+
+impl Tri {
+    /// `cfg(all(...))`: false if anything is known false, true only if
+    /// everything is known true, unknown otherwise.
+    fn all(preds: impl Iterator<Item = Tri>) -> Tri {
+        preds.fold(Tri::True, |acc, p| match (acc, p) {
+            (Tri::False, _) | (_, Tri::False) => Tri::False,
+            (Tri::True, Tri::True) => Tri::True,
+            _ => Tri::Unknown,
+        })
+    }
+
+    /// `cfg(any(...))`: true if anything is known true, false only if
+    /// everything is known false, unknown otherwise.
+    fn any(preds: impl Iterator<Item = Tri>) -> Tri {
+        preds.fold(Tri::False, |acc, p| match (acc, p) {
+            (Tri::True, _) | (_, Tri::True) => Tri::True,
+            (Tri::False, Tri::False) => Tri::False,
+            _ => Tri::Unknown,
+        })
+    }
+}
+
+impl ActiveConfig {
+    /// No features, no target, `test` off: feature and target predicates
+    /// are indeterminate and so scanned, while `#[cfg(test)]` code is
+    /// skipped.
+    pub fn none() -> Self {
+        Default::default()
+    }
+
+    fn lit_str(expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => Some(s.value()),
+            _ => None,
+        }
+    }
+
+    /// Evaluate a single `cfg(...)` predicate (the inner `syn::Meta`, e.g.
+    /// the `feature = "x"` in `#[cfg(feature = "x")]`) against this
+    /// configuration. Unknown/custom predicates are conservatively treated
+    /// as [`Tri::Unknown`], so we don't silently drop code we don't
+    /// understand (nor its negation -- see [`Tri`]).
+    fn eval(&self, meta: &syn::Meta) -> Tri {
+        match meta {
+            syn::Meta::Path(p) => {
+                if p.is_ident("test") {
+                    if self.test {
+                        Tri::True
+                    } else {
+                        Tri::False
+                    }
+                } else {
+                    Tri::Unknown
+                }
+            }
+            syn::Meta::NameValue(nv) => {
+                let Some(value) = Self::lit_str(&nv.value) else {
+                    return Tri::Unknown;
+                };
+                if nv.path.is_ident("feature") {
+                    // An empty feature set means "no features configured",
+                    // not "every feature is off" -- we don't know whether
+                    // this one is active.
+                    if self.features.is_empty() {
+                        Tri::Unknown
+                    } else if self.features.contains(&value) {
+                        Tri::True
+                    } else {
+                        Tri::False
+                    }
+                } else if nv.path.is_ident("target_os") {
+                    match &self.target_os {
+                        None => Tri::Unknown,
+                        Some(os) if *os == value => Tri::True,
+                        Some(_) => Tri::False,
+                    }
+                } else if nv.path.is_ident("target_arch") {
+                    match &self.target_arch {
+                        None => Tri::Unknown,
+                        Some(arch) if *arch == value => Tri::True,
+                        Some(_) => Tri::False,
+                    }
+                } else {
+                    Tri::Unknown
+                }
+            }
+            syn::Meta::List(l) => {
+                let args = l.parse_args_with(
+                    syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+                );
+                let Ok(args) = args else {
+                    return Tri::Unknown;
+                };
+                if l.path.is_ident("not") {
+                    args.first().map_or(Tri::Unknown, |m| !self.eval(m))
+                } else if l.path.is_ident("all") {
+                    Tri::all(args.iter().map(|m| self.eval(m)))
+                } else if l.path.is_ident("any") {
+                    Tri::any(args.iter().map(|m| self.eval(m)))
+                } else {
+                    Tri::Unknown
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `attr` is a `#[cfg(...)]` attribute whose predicate
+    /// is *known* to be false under this configuration -- an indeterminate
+    /// predicate is scanned, not excluded.
+    fn excludes(&self, attr: &syn::Attribute) -> bool {
+        if !attr.path().is_ident("cfg") {
+            return false;
+        }
+        let syn::Meta::List(l) = &attr.meta else {
+            return false;
+        };
+        match l.parse_args::<syn::Meta>() {
+            Ok(meta) => self.eval(&meta) == Tri::False,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Whether an effect was found directly in the written source, or only
+/// surfaced after expanding macros (see `scan_crate_expanded`) -- e.g. an
+/// FFI call hidden inside a `println!`-style or custom macro invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectOrigin {
+    Direct,
+    Expanded,
+}
+
+impl Default for EffectOrigin {
+    fn default() -> Self {
+        EffectOrigin::Direct
+    }
+}
+
+/// Which kind of Cargo target an effect was found in, and (where
+/// applicable) the target's name -- e.g. `Bin("cargo-scan")` for
+/// `src/bin/cargo-scan.rs`. Attached to effects by `scan_crate_all_targets`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetKind {
+    Lib,
+    Bin(String),
+    Example(String),
+    Test(String),
+    Bench(String),
+    /// `build.rs`: executes at build time, not when the crate's own code
+    /// runs, so callers typically want to call this out separately.
+    BuildScript,
+}
+
+impl Default for TargetKind {
+    fn default() -> Self {
+        TargetKind::Lib
+    }
+}
+
+/// One Cargo target inside a crate, together with the file
+/// `resolve_module_tree_from_root` should start from to find everything
+/// reachable from it.
+#[derive(Debug, Clone)]
+struct CargoTarget {
+    kind: TargetKind,
+    root: PathBuf,
+}
 
 /// Lines of Code tracker
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct LoCTracker {
     instances: usize,
     lines: usize,
@@ -64,6 +281,13 @@ impl LoCTracker {
     pub fn as_loc(&self) -> usize {
         self.lines + self.zero_size_lines
     }
+
+    /// Fold another tracker's counts into this one
+    pub fn merge(&mut self, other: Self) {
+        self.instances += other.instances;
+        self.lines += other.lines;
+        self.zero_size_lines += other.zero_size_lines;
+    }
 }
 
 /// Results of a scan
@@ -73,6 +297,22 @@ impl LoCTracker {
 #[derive(Debug, Default)]
 pub struct ScanResults {
     pub effects: Vec<EffectInstance>,
+    /// The cfg predicate (if any) guarding the effect at the same index in
+    /// `effects`, e.g. `Some("feature = \"foo\"")`. Kept in lockstep with
+    /// `effects` by every push site and by `sort_effects`.
+    pub effect_cfgs: Vec<Option<String>>,
+    /// Whether the effect at the same index in `effects` was written
+    /// directly or only appeared after macro expansion. Kept in lockstep the
+    /// same way `effect_cfgs` is.
+    pub effect_origins: Vec<EffectOrigin>,
+    /// Which Cargo target (lib, a bin, an example, a test, a bench, or
+    /// `build.rs`) the effect at the same index in `effects` was found in.
+    /// Only meaningfully varied when produced by `scan_crate_all_targets`;
+    /// every other entry point tags its effects `TargetKind::Lib`, except
+    /// effects under a `#[cfg(test)]` guard, which are tagged
+    /// `TargetKind::Test` by every entry point. Kept in
+    /// lockstep the same way `effect_cfgs` is.
+    pub effect_targets: Vec<TargetKind>,
     pub effect_blocks: Vec<EffectBlock>,
 
     pub unsafe_traits: Vec<TraitDec>,
@@ -113,10 +353,7 @@ impl ScanResults {
             .collect::<HashSet<_>>()
     }
 
-    pub fn get_callers<'a>(
-        &'a self,
-        callee: &CanonicalPath,
-    ) -> HashSet<&'a EffectInstance> {
+    pub fn get_callers<'a>(&'a self, callee: &CanonicalPath) -> HashSet<&'a EffectInstance> {
         let mut callers = HashSet::new();
         for e in &self.effects {
             let effect_callee = e.callee();
@@ -141,6 +378,253 @@ impl ScanResults {
         }
         self.fn_locs.insert(fn_name, f.src_loc);
     }
+
+    /// Fold another (independently scanned) set of results into this one.
+    ///
+    /// Used to merge the per-file results produced by scanning files in
+    /// parallel; the caller is responsible for calling `sort_effects`
+    /// afterwards if a deterministic effect order is required.
+    pub fn merge(&mut self, other: Self) {
+        // Re-add the other scan's call graph nodes under fresh indices,
+        // remembering the remapping so its edges can be re-added below.
+        let mut node_remap = HashMap::new();
+        for (path, idx) in &other.node_idxs {
+            let new_idx = *self
+                .node_idxs
+                .entry(path.clone())
+                .or_insert_with(|| self.call_graph.add_node(path.clone()));
+            node_remap.insert(*idx, new_idx);
+        }
+        for edge in other.call_graph.edge_indices() {
+            if let Some((src, dst)) = other.call_graph.edge_endpoints(edge) {
+                if let (Some(&new_src), Some(&new_dst)) =
+                    (node_remap.get(&src), node_remap.get(&dst))
+                {
+                    self.call_graph
+                        .add_edge(new_src, new_dst, other.call_graph[edge].clone());
+                }
+            }
+        }
+
+        self.effects.extend(other.effects);
+        self.effect_cfgs.extend(other.effect_cfgs);
+        self.effect_origins.extend(other.effect_origins);
+        self.effect_targets.extend(other.effect_targets);
+        self.effect_blocks.extend(other.effect_blocks);
+        self.unsafe_traits.extend(other.unsafe_traits);
+        self.unsafe_impls.extend(other.unsafe_impls);
+        self.pub_fns.extend(other.pub_fns);
+        self.fn_locs.extend(other.fn_locs);
+
+        self.total_loc.merge(other.total_loc);
+        self.skipped_macros.merge(other.skipped_macros);
+        self.skipped_conditional_code
+            .merge(other.skipped_conditional_code);
+        self.skipped_fn_calls.merge(other.skipped_fn_calls);
+        self.skipped_other.merge(other.skipped_other);
+        self._effects_loc.merge(other._effects_loc);
+        self._skipped_attributes.merge(other._skipped_attributes);
+        self._skipped_build_rs.merge(other._skipped_build_rs);
+    }
+
+    /// Sort effects by call site (file, then line, then column) so that the
+    /// output order -- and thus the saved `CheckFile` order -- is stable no
+    /// matter which thread happened to scan which file.
+    pub fn sort_effects(&mut self) {
+        let mut paired: Vec<(EffectInstance, Option<String>, EffectOrigin, TargetKind)> = self
+            .effects
+            .drain(..)
+            .zip(self.effect_cfgs.drain(..))
+            .zip(self.effect_origins.drain(..))
+            .zip(self.effect_targets.drain(..))
+            .map(|(((effect, cfg), origin), target)| (effect, cfg, origin, target))
+            .collect();
+        paired.sort_by(|(a, _, _, _), (b, _, _, _)| {
+            let a_loc = a.call_loc();
+            let b_loc = b.call_loc();
+            a_loc
+                .file()
+                .cmp(b_loc.file())
+                .then_with(|| a_loc.line().cmp(&b_loc.line()))
+                .then_with(|| a_loc.column().cmp(&b_loc.column()))
+        });
+        for (effect, cfg, origin, target) in paired {
+            self.effects.push(effect);
+            self.effect_cfgs.push(cfg);
+            self.effect_origins.push(origin);
+            self.effect_targets.push(target);
+        }
+    }
+
+    /// Iterate effects paired with the Cargo target they were found in, as
+    /// tagged by `scan_crate_all_targets`.
+    pub fn effects_with_targets(&self) -> impl Iterator<Item = (&EffectInstance, &TargetKind)> {
+        self.effects.iter().zip(self.effect_targets.iter())
+    }
+
+    /// Effects found outside of test code -- what a supply-chain audit
+    /// normally cares about.
+    pub fn non_test_effects(&self) -> Vec<&EffectInstance> {
+        self.effects_with_targets()
+            .filter(|(_, t)| !matches!(t, TargetKind::Test(_)))
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    /// Effects found in `build.rs`, which run at build time rather than
+    /// when the crate's own code runs.
+    pub fn build_script_effects(&self) -> Vec<&EffectInstance> {
+        self.effects_with_targets()
+            .filter(|(_, t)| matches!(t, TargetKind::BuildScript))
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    /// Functions that transitively call `callee` through one or more hops of
+    /// the call graph -- e.g. a caller in one crate reaching a sink flagged
+    /// in another, once the two crates' `ScanResults` have been `merge`d.
+    pub fn transitive_callers(&self, callee: &CanonicalPath) -> HashSet<CanonicalPath> {
+        let Some(&start) = self.node_idxs.get(callee) else {
+            return HashSet::new();
+        };
+
+        let mut seen = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(idx) = stack.pop() {
+            for caller_idx in self.call_graph.neighbors_directed(idx, Direction::Incoming) {
+                if seen.insert(caller_idx) {
+                    stack.push(caller_idx);
+                }
+            }
+        }
+        seen.into_iter()
+            .map(|idx| self.call_graph[idx].clone())
+            .collect()
+    }
+
+    /// Flatten into the on-disk format: `DiGraph` node indices are only
+    /// meaningful within one in-memory graph, so the call graph is stored as
+    /// a plain edge list keyed on `CanonicalPath` and rebuilt via
+    /// `from_serialized`.
+    fn to_serialized(&self) -> SerializedScanResults {
+        let edges = self
+            .call_graph
+            .edge_indices()
+            .filter_map(|e| {
+                let (src, dst) = self.call_graph.edge_endpoints(e)?;
+                Some((
+                    self.call_graph[src].clone(),
+                    self.call_graph[dst].clone(),
+                    self.call_graph[e].clone(),
+                ))
+            })
+            .collect();
+
+        SerializedScanResults {
+            effects: self.effects.clone(),
+            effect_cfgs: self.effect_cfgs.clone(),
+            effect_origins: self.effect_origins.clone(),
+            effect_targets: self.effect_targets.clone(),
+            effect_blocks: self.effect_blocks.clone(),
+            unsafe_traits: self.unsafe_traits.clone(),
+            unsafe_impls: self.unsafe_impls.clone(),
+            pub_fns: self.pub_fns.clone(),
+            fn_locs: self.fn_locs.clone(),
+            nodes: self.node_idxs.keys().cloned().collect(),
+            call_edges: edges,
+            total_loc: self.total_loc.clone(),
+            skipped_macros: self.skipped_macros.clone(),
+            skipped_conditional_code: self.skipped_conditional_code.clone(),
+            skipped_fn_calls: self.skipped_fn_calls.clone(),
+            skipped_other: self.skipped_other.clone(),
+            _effects_loc: self._effects_loc.clone(),
+            _skipped_attributes: self._skipped_attributes.clone(),
+            _skipped_build_rs: self._skipped_build_rs.clone(),
+        }
+    }
+
+    fn from_serialized(s: SerializedScanResults) -> Self {
+        let mut call_graph = DiGraph::new();
+        let mut node_idxs = HashMap::new();
+        for node in s.nodes {
+            let idx = call_graph.add_node(node.clone());
+            node_idxs.insert(node, idx);
+        }
+        for (src, dst, loc) in s.call_edges {
+            if let (Some(&src_idx), Some(&dst_idx)) = (node_idxs.get(&src), node_idxs.get(&dst))
+            {
+                call_graph.add_edge(src_idx, dst_idx, loc);
+            }
+        }
+
+        Self {
+            effects: s.effects,
+            effect_cfgs: s.effect_cfgs,
+            effect_origins: s.effect_origins,
+            effect_targets: s.effect_targets,
+            effect_blocks: s.effect_blocks,
+            unsafe_traits: s.unsafe_traits,
+            unsafe_impls: s.unsafe_impls,
+            pub_fns: s.pub_fns,
+            fn_locs: s.fn_locs,
+            call_graph,
+            node_idxs,
+            total_loc: s.total_loc,
+            skipped_macros: s.skipped_macros,
+            skipped_conditional_code: s.skipped_conditional_code,
+            skipped_fn_calls: s.skipped_fn_calls,
+            skipped_other: s.skipped_other,
+            _effects_loc: s._effects_loc,
+            _skipped_attributes: s._skipped_attributes,
+            _skipped_build_rs: s._skipped_build_rs,
+        }
+    }
+
+    /// Save to `path` in the on-disk format described on `SerializedScanResults`,
+    /// so a later process (e.g. scanning a different crate) can load and
+    /// `merge` it without re-scanning.
+    pub fn save_to_file(&self, path: &FilePath) -> Result<()> {
+        let contents = serde_json::to_string(&self.to_serialized())?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Load a `ScanResults` previously written by `save_to_file`.
+    pub fn load_from_file(path: &FilePath) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let serialized: SerializedScanResults = serde_json::from_str(&contents)?;
+        Ok(Self::from_serialized(serialized))
+    }
+}
+
+/// On-disk mirror of `ScanResults`, used by `save_to_file`/`load_from_file` to
+/// serialize per-crate scan results to be merged across crate boundaries --
+/// similar in spirit to rustdoc's `scrape-examples` call data, which is
+/// scanned per-crate and stitched together by stable item identifiers.
+#[derive(Debug, Serialize, Deserialize)]
+struct SerializedScanResults {
+    effects: Vec<EffectInstance>,
+    effect_cfgs: Vec<Option<String>>,
+    effect_origins: Vec<EffectOrigin>,
+    effect_targets: Vec<TargetKind>,
+    effect_blocks: Vec<EffectBlock>,
+    unsafe_traits: Vec<TraitDec>,
+    unsafe_impls: Vec<TraitImpl>,
+    pub_fns: HashSet<CanonicalPath>,
+    fn_locs: HashMap<CanonicalPath, SrcLoc>,
+    /// Call graph nodes -- `DiGraph`'s `NodeIndex`es aren't stable across a
+    /// save/load boundary, so the graph is flattened to a node list plus an
+    /// edge list keyed on `CanonicalPath`.
+    nodes: Vec<CanonicalPath>,
+    call_edges: Vec<(CanonicalPath, CanonicalPath, SrcLoc)>,
+    total_loc: LoCTracker,
+    skipped_macros: LoCTracker,
+    skipped_conditional_code: LoCTracker,
+    skipped_fn_calls: LoCTracker,
+    skipped_other: LoCTracker,
+    _effects_loc: LoCTracker,
+    _skipped_attributes: LoCTracker,
+    _skipped_build_rs: LoCTracker,
 }
 
 /// Stateful object to scan Rust source code for effects (fn calls of interest)
@@ -175,6 +659,24 @@ pub struct Scanner<'a> {
 
     /// The list of sinks to look for
     sinks: HashSet<IdentPath>,
+
+    /// The active feature/target/test configuration, used to decide whether
+    /// a `#[cfg(...)]`-guarded item is reachable under it
+    active_cfg: ActiveConfig,
+
+    /// Stack of cfg predicates (as written) guarding the current scope,
+    /// innermost last; attached to effects pushed while non-empty
+    scope_cfg: Vec<String>,
+
+    /// Set when this scanner is running over macro-expanded source (see
+    /// `scan_crate_expanded`), so effects it finds get tagged
+    /// `EffectOrigin::Expanded` instead of `EffectOrigin::Direct`.
+    expanding: bool,
+
+    /// The Cargo target this scanner is currently scanning, used to tag
+    /// effects (see `scan_crate_all_targets`). Defaults to `Lib`, matching
+    /// every other entry point, which only ever scans the library tree.
+    current_target: TargetKind,
 }
 
 impl<'a> Scanner<'a> {
@@ -187,6 +689,18 @@ impl<'a> Scanner<'a> {
         filepath: &'a FilePath,
         resolver: FileResolver<'a>,
         data: &'a mut ScanResults,
+    ) -> Self {
+        Self::new_with_cfg(filepath, resolver, data, ActiveConfig::none())
+    }
+
+    /// Create a new scanner, evaluating `#[cfg(...)]` predicates against the
+    /// supplied active configuration as it descends (`#[cfg_attr(...)]` is
+    /// not evaluated)
+    pub fn new_with_cfg(
+        filepath: &'a FilePath,
+        resolver: FileResolver<'a>,
+        data: &'a mut ScanResults,
+        active_cfg: ActiveConfig,
     ) -> Self {
         Self {
             filepath,
@@ -197,9 +711,25 @@ impl<'a> Scanner<'a> {
             scope_fns: Vec::new(),
             data,
             sinks: Sink::default_sinks(),
+            active_cfg,
+            scope_cfg: Vec::new(),
+            expanding: false,
+            current_target: TargetKind::default(),
         }
     }
 
+    /// Mark that this scanner is running over macro-expanded source, so
+    /// effects it records are tagged `EffectOrigin::Expanded`.
+    pub fn mark_expanded(&mut self) {
+        self.expanding = true;
+    }
+
+    /// Set the Cargo target this scanner is scanning, so effects it records
+    /// get tagged with it (see `scan_crate_all_targets`).
+    pub fn set_target(&mut self, target: TargetKind) {
+        self.current_target = target;
+    }
+
     /// Top-level invariant -- called before consuming results
     pub fn assert_top_level_invariant(&self) {
         self.resolver.assert_top_level_invariant();
@@ -248,30 +778,93 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    // Quickfix to decide when to skip a CFG attribute
-    // TODO: we need to use rust-analyzer or similar to more robustly parse attributes
-    pub fn skip_cfg(&self, args: &str) -> bool {
-        args.starts_with("target_os = \"linux\"") || args.starts_with("not (feature =")
+    // Return true if a `#[cfg(...)]` attribute's predicate is statically
+    // false under the scanner's active configuration (enabled features,
+    // target, `test`)
+    pub fn skip_cfg(&self, attr: &'a syn::Attribute) -> bool {
+        self.active_cfg.excludes(attr)
     }
 
     // Return true if the attributes imply the code should be skipped
     pub fn skip_attr(&self, attr: &'a syn::Attribute) -> bool {
-        let path = attr.path();
-        // if path.is_ident("cfg_args") || path.is_ident("cfg") {
-        if path.is_ident("cfg") {
-            let syn::Meta::List(l) = &attr.meta else { return false };
-            let args = &l.tokens;
-            if self.skip_cfg(args.to_string().as_str()) {
-                info!("Skipping cfg attribute: {}", args);
+        if attr.path().is_ident("cfg") {
+            if self.skip_cfg(attr) {
+                info!("Skipping cfg attribute: {}", attr.to_token_stream());
                 return true;
             } else {
-                debug!("Scanning cfg attribute: {}", args);
+                debug!("Scanning cfg attribute: {}", attr.to_token_stream());
                 return false;
             }
         }
         false
     }
 
+    /// The cfg predicate (if any) currently guarding the scope an effect is
+    /// found in: the predicates of every enclosing `cfg`, outermost first,
+    /// joined with ` && `. Kept alongside every effect so a
+    /// consumer can tell whether it's reachable under the scanner's active
+    /// configuration.
+    fn current_cfg(&self) -> Option<String> {
+        if self.scope_cfg.is_empty() {
+            None
+        } else {
+            Some(self.scope_cfg.join(" && "))
+        }
+    }
+
+    /// The origin to tag an effect found right now with, based on whether
+    /// this scanner is running over macro-expanded source.
+    fn effect_origin(&self) -> EffectOrigin {
+        if self.expanding {
+            EffectOrigin::Expanded
+        } else {
+            EffectOrigin::Direct
+        }
+    }
+
+    /// The target to tag an effect found right now with. A `#[cfg(test)]`
+    /// guard anywhere in the current cfg stack wins over `current_target`,
+    /// so inline unit tests inside the library are tagged as test code even
+    /// though they're scanned as part of the `Lib` target.
+    fn effect_target(&self) -> TargetKind {
+        if self.scope_cfg.iter().any(|c| c == "test") {
+            let name = match &self.current_target {
+                TargetKind::Lib => "lib".to_string(),
+                other => format!("{:?}", other),
+            };
+            TargetKind::Test(name)
+        } else {
+            self.current_target.clone()
+        }
+    }
+
+    /// Record that `attrs` (already confirmed reachable by `skip_attrs`) may
+    /// carry a `cfg` guard, pushing its predicate text onto `scope_cfg` for
+    /// the duration of `body`, so effects found within get attributed to it.
+    fn with_cfg_guard<R>(
+        &mut self,
+        attrs: &'a [syn::Attribute],
+        body: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let guard = attrs.iter().find_map(|attr| {
+            if !attr.path().is_ident("cfg") {
+                return None;
+            }
+            let syn::Meta::List(l) = &attr.meta else {
+                return None;
+            };
+            Some(l.tokens.to_string())
+        });
+        if let Some(guard) = &guard {
+            self.scope_cfg.push(guard.clone());
+        }
+        let result = body(self);
+        if guard.is_some() {
+            self.scope_cfg.pop();
+        }
+        result
+    }
+
     // Return true if the attributes imply the code should be skipped
     pub fn skip_attrs(&self, attrs: &'a [syn::Attribute]) -> bool {
         attrs.iter().any(|x| self.skip_attr(x))
@@ -283,13 +876,15 @@ impl<'a> Scanner<'a> {
             return;
         }
 
-        if let Some((_, items)) = &m.content {
-            self.resolver.push_mod(&m.ident);
-            for i in items {
-                self.scan_item(i);
+        self.with_cfg_guard(&m.attrs, |this| {
+            if let Some((_, items)) = &m.content {
+                this.resolver.push_mod(&m.ident);
+                for i in items {
+                    this.scan_item(i);
+                }
+                this.resolver.pop_mod();
             }
-            self.resolver.pop_mod();
-        }
+        });
     }
 
     /*
@@ -312,9 +907,11 @@ impl<'a> Scanner<'a> {
         }
 
         self.scope_unsafe += 1;
-        for i in &fm.items {
-            self.scan_foreign_item(i);
-        }
+        self.with_cfg_guard(&fm.attrs, |this| {
+            for i in &fm.items {
+                this.scan_foreign_item(i);
+            }
+        });
         self.scope_unsafe -= 1;
     }
 
@@ -346,7 +943,9 @@ impl<'a> Scanner<'a> {
         let t_unsafety = t.unsafety;
         if t_unsafety.is_some() {
             // we found an `unsafe trait` declaration
-            self.data.unsafe_traits.push(TraitDec::new(t, self.filepath, t_name));
+            self.data
+                .unsafe_traits
+                .push(TraitDec::new(t, self.filepath, t_name));
         }
         // TBD: handle trait block, e.g. default implementations
     }
@@ -363,22 +962,24 @@ impl<'a> Scanner<'a> {
             self.scan_impl_trait_path(tr, imp);
         }
 
-        for item in &imp.items {
-            match item {
-                syn::ImplItem::Fn(m) => {
-                    self.scan_method(m);
-                }
-                syn::ImplItem::Macro(m) => {
-                    self.data.skipped_macros.add(m);
-                }
-                syn::ImplItem::Verbatim(v) => {
-                    self.syn_warning("skipping Verbatim expression", v);
-                }
-                other => {
-                    self.data.skipped_other.add(other);
+        self.with_cfg_guard(&imp.attrs, |this| {
+            for item in &imp.items {
+                match item {
+                    syn::ImplItem::Fn(m) => {
+                        this.scan_method(m);
+                    }
+                    syn::ImplItem::Macro(m) => {
+                        this.data.skipped_macros.add(m);
+                    }
+                    syn::ImplItem::Verbatim(v) => {
+                        this.syn_warning("skipping Verbatim expression", v);
+                    }
+                    other => {
+                        this.data.skipped_other.add(other);
+                    }
                 }
             }
-        }
+        });
 
         self.resolver.pop_impl();
     }
@@ -402,12 +1003,9 @@ impl<'a> Scanner<'a> {
                 _ => None,
             };
 
-            self.data.unsafe_impls.push(TraitImpl::new(
-                imp,
-                self.filepath,
-                tr_name,
-                tr_type,
-            ));
+            self.data
+                .unsafe_impls
+                .push(TraitImpl::new(imp, self.filepath, tr_name, tr_type));
         }
     }
 
@@ -421,7 +1019,7 @@ impl<'a> Scanner<'a> {
             return;
         }
 
-        self.scan_fn(&f.sig, &f.block, &f.vis);
+        self.with_cfg_guard(&f.attrs, |this| this.scan_fn(&f.sig, &f.block, &f.vis));
     }
 
     fn scan_method(&mut self, m: &'a syn::ImplItemFn) {
@@ -431,7 +1029,7 @@ impl<'a> Scanner<'a> {
         }
 
         // NB: may or may not be a method, if there is no self keyword
-        self.scan_fn(&m.sig, &m.block, &m.vis);
+        self.with_cfg_guard(&m.attrs, |this| this.scan_fn(&m.sig, &m.block, &m.vis));
     }
 
     fn scan_fn(
@@ -476,7 +1074,9 @@ impl<'a> Scanner<'a> {
         self.resolver.pop_fn();
 
         // Save effect block
-        self.data.effect_blocks.push(self.scope_effect_blocks.pop().unwrap());
+        self.data
+            .effect_blocks
+            .push(self.scope_effect_blocks.pop().unwrap());
         if f_unsafety.is_some() {
             debug_assert!(self.scope_unsafe >= 1);
             self.scope_unsafe -= 1;
@@ -500,12 +1100,17 @@ impl<'a> Scanner<'a> {
     }
 
     fn scan_fn_local(&mut self, l: &'a syn::Local) {
-        if let Some(let_expr) = &l.init {
-            self.scan_expr(&let_expr.expr);
-            if let Some((_, else_expr)) = &let_expr.diverge {
-                self.scan_expr(else_expr);
-            }
+        if self.skip_attrs(&l.attrs) {
+            return;
         }
+        self.with_cfg_guard(&l.attrs, |this| {
+            if let Some(let_expr) = &l.init {
+                this.scan_expr(&let_expr.expr);
+                if let Some((_, else_expr)) = &let_expr.diverge {
+                    this.scan_expr(else_expr);
+                }
+            }
+        });
     }
 
     /*
@@ -761,6 +1366,9 @@ impl<'a> Scanner<'a> {
     }
 
     fn scan_unsafe_block(&mut self, x: &'a syn::ExprUnsafe) {
+        if self.skip_attrs(&x.attrs) {
+            return;
+        }
         self.scope_unsafe += 1;
 
         // We will always be in a function definition inside of a block, so it
@@ -771,10 +1379,14 @@ impl<'a> Scanner<'a> {
             self.scope_fns.last().unwrap().clone(),
         );
         self.scope_effect_blocks.push(effect_block);
-        for s in &x.block.stmts {
-            self.scan_fn_statement(s);
-        }
-        self.data.effect_blocks.push(self.scope_effect_blocks.pop().unwrap());
+        self.with_cfg_guard(&x.attrs, |this| {
+            for s in &x.block.stmts {
+                this.scan_fn_statement(s);
+            }
+        });
+        self.data
+            .effect_blocks
+            .push(self.scope_effect_blocks.pop().unwrap());
 
         self.scope_unsafe -= 1;
     }
@@ -805,13 +1417,8 @@ impl<'a> Scanner<'a> {
             }
         };
 
-        let eff = EffectInstance::new_effect(
-            self.filepath,
-            caller,
-            callee,
-            &eff_span,
-            eff_type,
-        );
+        let eff =
+            EffectInstance::new_effect(self.filepath, caller, callee, &eff_span, eff_type);
         if let Some(effect_block) = self.scope_effect_blocks.last_mut() {
             effect_block.push_effect(eff.clone())
         }
@@ -819,6 +1426,9 @@ impl<'a> Scanner<'a> {
         //     self.syn_warning("unexpected effect found outside an effect block", eff_span);
         // }
         self.data.effects.push(eff);
+        self.data.effect_cfgs.push(self.current_cfg());
+        self.data.effect_origins.push(self.effect_origin());
+        self.data.effect_targets.push(self.effect_target());
     }
 
     /// push an Effect to the list of results based on this call site.
@@ -831,7 +1441,11 @@ impl<'a> Scanner<'a> {
     ) where
         S: Debug + Spanned,
     {
-        let caller = &self.scope_fns.last().expect("not inside a function!").fn_name;
+        let caller = &self
+            .scope_fns
+            .last()
+            .expect("not inside a function!")
+            .fn_name;
         if let Some(caller_node_idx) = self.data.node_idxs.get(caller) {
             if let Some(callee_node_idx) = self.data.node_idxs.get(&callee) {
                 self.data.call_graph.add_edge(
@@ -860,6 +1474,9 @@ impl<'a> Scanner<'a> {
             );
         }
         self.data.effects.push(eff);
+        self.data.effect_cfgs.push(self.current_cfg());
+        self.data.effect_origins.push(self.effect_origin());
+        self.data.effect_targets.push(self.effect_target());
     }
 
     fn scan_expr_call(&mut self, f: &'a syn::Expr) {
@@ -894,8 +1511,7 @@ impl<'a> Scanner<'a> {
     fn scan_expr_call_field(&mut self, m: &'a syn::Member) {
         match m {
             syn::Member::Named(i) => {
-                let is_unsafe =
-                    self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
+                let is_unsafe = self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
                 self.push_callsite(i, self.resolver.resolve_field(i), None, is_unsafe);
             }
             syn::Member::Unnamed(idx) => {
@@ -915,15 +1531,26 @@ impl<'a> Scanner<'a> {
     }
 }
 
-/// Load the Rust file at the filepath and scan it
+/// Load the Rust file at the filepath and scan it.
+///
+/// `mod_prefix` is the file's real module path relative to the crate root
+/// (e.g. `["a", "b"]` for a file mounted at `crate::a::b`), as determined by
+/// following `mod` declarations from the crate root -- see
+/// `resolve_crate_modules`. It's seeded into the resolver before scanning so
+/// canonical paths for items in this file come out correctly rooted.
 pub fn scan_file(
     crate_name: &str,
     filepath: &FilePath,
+    mod_prefix: &[String],
     resolver: &Resolver,
     scan_results: &mut ScanResults,
     sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
 ) -> Result<()> {
-    debug!("Scanning file: {:?}", filepath);
+    debug!(
+        "Scanning file: {:?} (module path: {:?})",
+        filepath, mod_prefix
+    );
 
     // Load file contents
     let mut file = File::open(filepath)?;
@@ -932,8 +1559,16 @@ pub fn scan_file(
     let syntax_tree = syn::parse_file(&src)?;
 
     // Initialize data structures
-    let file_resolver = FileResolver::new(crate_name, resolver, filepath)?;
-    let mut scanner = Scanner::new(filepath, file_resolver, scan_results);
+    let mut file_resolver = FileResolver::new(crate_name, resolver, filepath)?;
+    let seed_idents: Vec<syn::Ident> = mod_prefix
+        .iter()
+        .map(|seg| syn::Ident::new(seg, proc_macro2::Span::call_site()))
+        .collect();
+    for ident in &seed_idents {
+        file_resolver.push_mod(ident);
+    }
+
+    let mut scanner = Scanner::new_with_cfg(filepath, file_resolver, scan_results, active_cfg);
     scanner.add_sinks(sinks);
 
     // Scan file contents
@@ -946,33 +1581,227 @@ pub fn scan_file(
 pub fn try_scan_file(
     crate_name: &str,
     filepath: &FilePath,
+    mod_prefix: &[String],
     resolver: &Resolver,
     scan_results: &mut ScanResults,
     sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
 ) {
-    scan_file(crate_name, filepath, resolver, scan_results, sinks).unwrap_or_else(
-        |err| {
-            warn!("Failed to scan file: {} ({})", filepath.to_string_lossy(), err);
-        },
-    );
+    scan_file(
+        crate_name,
+        filepath,
+        mod_prefix,
+        resolver,
+        scan_results,
+        sinks,
+        active_cfg,
+    )
+    .unwrap_or_else(|err| {
+        warn!(
+            "Failed to scan file: {} ({})",
+            filepath.to_string_lossy(),
+            err
+        );
+    });
+}
+
+/// A file reachable from the crate root, together with the module path
+/// (e.g. `["a", "b"]` for `crate::a::b`) it's mounted at.
+#[derive(Debug, Clone)]
+struct ModuleFile {
+    path: PathBuf,
+    mod_path: Vec<String>,
+}
+
+/// Names that are never worth re-entering as a module file, since they
+/// resolve to std/core/alloc rather than to something in this crate.
+fn is_std_module(ident: &syn::Ident) -> bool {
+    matches!(ident.to_string().as_str(), "std" | "core" | "alloc")
+}
+
+/// Resolve the explicit `#[path = "..."]` attribute on a `mod` item, if any.
+fn explicit_mod_path(attrs: &[syn::Attribute]) -> Option<String> {
+    for attr in attrs {
+        if attr.path().is_ident("path") {
+            if let syn::Meta::NameValue(nv) = &attr.meta {
+                if let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) = &nv.value
+                {
+                    return Some(s.value());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The directory that `mod foo;` declarations inside `file` resolve
+/// relative to: the file's own directory for `lib.rs`/`main.rs`/`mod.rs`,
+/// or a same-named subdirectory otherwise (e.g. `a.rs`'s submodules live
+/// under `a/`).
+fn submodule_dir(file: &FilePath) -> PathBuf {
+    let parent = file.parent().unwrap_or_else(|| FilePath::new(""));
+    match file.file_stem().and_then(|s| s.to_str()) {
+        Some("lib") | Some("main") | Some("mod") | None => parent.to_path_buf(),
+        Some(stem) => parent.join(stem),
+    }
+}
+
+/// Recursively follow `mod` items (both inline `mod foo { .. }` blocks and
+/// `mod foo;` file declarations) starting from the items of one file/block,
+/// pushing every reachable file into `out`.
+fn resolve_mod_items(
+    items: &[syn::Item],
+    submod_dir: &FilePath,
+    mod_path: &[String],
+    active_cfg: &ActiveConfig,
+    out: &mut Vec<ModuleFile>,
+) -> Result<()> {
+    for item in items {
+        let syn::Item::Mod(m) = item else { continue };
+        if is_std_module(&m.ident) {
+            continue;
+        }
+        // A `mod` item can itself be `#[cfg(...)]`-guarded (e.g. `#[cfg(feature =
+        // "x")] mod x;`); don't descend into a module that's statically excluded
+        // under the active configuration, or its effects get reported anyway.
+        if m.attrs.iter().any(|attr| active_cfg.excludes(attr)) {
+            continue;
+        }
+
+        let mut child_path = mod_path.to_vec();
+        child_path.push(m.ident.to_string());
+
+        if let Some((_, inline_items)) = &m.content {
+            // `mod foo { ... }` -- stays in the same file, but may itself
+            // contain further `mod bar;` file declarations relative to a
+            // subdirectory named after this inline module.
+            let inline_dir = submod_dir.join(m.ident.to_string());
+            resolve_mod_items(inline_items, &inline_dir, &child_path, active_cfg, out)?;
+            continue;
+        }
+
+        // `mod foo;` -- resolve to foo.rs, foo/mod.rs, or an explicit #[path]
+        let candidate = match explicit_mod_path(&m.attrs) {
+            Some(p) => submod_dir.join(p),
+            None => {
+                let direct = submod_dir.join(format!("{}.rs", m.ident));
+                if direct.is_file() {
+                    direct
+                } else {
+                    submod_dir.join(m.ident.to_string()).join("mod.rs")
+                }
+            }
+        };
+
+        if candidate.is_file() {
+            resolve_module_file(&candidate, child_path, active_cfg, out)?;
+        } else {
+            warn!(
+                "Could not resolve `mod {};` to a file (looked for {:?})",
+                m.ident, candidate
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parse `file`, record it, and recurse into the `mod` items it declares.
+fn resolve_module_file(
+    file: &FilePath,
+    mod_path: Vec<String>,
+    active_cfg: &ActiveConfig,
+    out: &mut Vec<ModuleFile>,
+) -> Result<()> {
+    let mut src = String::new();
+    File::open(file)?.read_to_string(&mut src)?;
+    let syntax = syn::parse_file(&src)?;
+
+    let submod_dir = submodule_dir(file);
+    out.push(ModuleFile {
+        path: file.to_path_buf(),
+        mod_path: mod_path.clone(),
+    });
+    resolve_mod_items(&syntax.items, &submod_dir, &mod_path, active_cfg, out)
+}
+
+/// Walk the module tree reachable from the crate's root file (`lib.rs` or
+/// `main.rs`), following `mod foo;`/`mod foo { .. }` declarations (including
+/// `#[path = "..."]` overrides) instead of globbing every `.rs` file under
+/// `src/`. This avoids double-scanning unreachable files and lets each
+/// scanned file be attributed to its real module path.
+fn resolve_crate_modules(
+    crate_path: &FilePath,
+    active_cfg: &ActiveConfig,
+) -> Result<Vec<ModuleFile>> {
+    let src_dir = crate_path.join(FilePath::new("src"));
+    let root_file = {
+        let lib_rs = src_dir.join("lib.rs");
+        let main_rs = src_dir.join("main.rs");
+        if lib_rs.is_file() {
+            lib_rs
+        } else if main_rs.is_file() {
+            main_rs
+        } else {
+            return Err(anyhow!(
+                "crate has neither src/lib.rs nor src/main.rs: {:?}",
+                crate_path
+            ));
+        }
+    };
+
+    resolve_module_tree_from_root(&root_file, active_cfg)
+}
+
+/// Like `resolve_crate_modules`, but starting from an arbitrary root file
+/// instead of assuming `src/lib.rs`/`src/main.rs` -- used by
+/// `scan_crate_all_targets` so each Cargo target's own submodules (e.g.
+/// `src/bin/foo/helper.rs` declared via `mod helper;` in `src/bin/foo.rs`)
+/// are picked up too.
+fn resolve_module_tree_from_root(
+    root_file: &FilePath,
+    active_cfg: &ActiveConfig,
+) -> Result<Vec<ModuleFile>> {
+    let mut files = Vec::new();
+    resolve_module_file(root_file, Vec::new(), active_cfg, &mut files)?;
+    Ok(files)
 }
 
 /// Scan the supplied crate with an additional list of sinks
 pub fn scan_crate_with_sinks(
     crate_path: &FilePath,
     sinks: HashSet<IdentPath>,
+) -> Result<ScanResults> {
+    scan_crate_with_cfg(crate_path, sinks, ActiveConfig::none())
+}
+
+/// Scan the supplied crate with an additional list of sinks, evaluating
+/// `#[cfg(...)]`-guarded code against `active_cfg` instead of treating every
+/// predicate as reachable.
+pub fn scan_crate_with_cfg(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
 ) -> Result<ScanResults> {
     info!("Scanning crate: {:?}", crate_path);
 
     // Make sure the path is a crate
     if !crate_path.is_dir() {
-        return Err(anyhow!("Path is not a crate; not a directory: {:?}", crate_path));
+        return Err(anyhow!(
+            "Path is not a crate; not a directory: {:?}",
+            crate_path
+        ));
     }
 
     let mut cargo_toml_path = crate_path.to_path_buf();
     cargo_toml_path.push("Cargo.toml");
     if !cargo_toml_path.try_exists()? || !cargo_toml_path.is_file() {
-        return Err(anyhow!("Path is not a crate; missing Cargo.toml: {:?}", crate_path));
+        return Err(anyhow!(
+            "Path is not a crate; missing Cargo.toml: {:?}",
+            crate_path
+        ));
     }
 
     let crate_name = util::load_cargo_toml(crate_path)?.name;
@@ -981,36 +1810,58 @@ pub fn scan_crate_with_sinks(
 
     let mut scan_results = ScanResults::new();
 
-    // TODO: For now, only walking through the src dir, but might want to
-    //       include others (e.g. might codegen in other dirs)
-    let src_dir = crate_path.join(FilePath::new("src"));
-    if src_dir.is_dir() {
-        for entry in util::fs::walk_files_with_extension(&src_dir, "rs") {
-            try_scan_file(
-                &crate_name,
-                entry.as_path(),
-                &resolver,
-                &mut scan_results,
-                sinks.clone(),
-            );
+    match resolve_crate_modules(crate_path, &active_cfg) {
+        Ok(files) => {
+            // Scan each reachable module file in parallel, each into its own
+            // ScanResults, and merge; the file list (and thus thread
+            // assignment) is collected up front in a stable order so output
+            // doesn't depend on thread scheduling.
+            let partials: Vec<ScanResults> = files
+                .par_iter()
+                .map(|module_file| {
+                    let mut partial = ScanResults::new();
+                    try_scan_file(
+                        &crate_name,
+                        &module_file.path,
+                        &module_file.mod_path,
+                        &resolver,
+                        &mut partial,
+                        sinks.clone(),
+                        active_cfg.clone(),
+                    );
+                    partial
+                })
+                .collect();
+            for partial in partials {
+                scan_results.merge(partial);
+            }
+            // The interactive vetting order and the saved CheckFile order must
+            // not depend on which thread scanned which file.
+            scan_results.sort_effects();
         }
-    } else {
-        info!("crate has no src dir; looking for a single lib.rs file instead");
-        let lib_file = crate_path.join(FilePath::new("lib.rs"));
-        if lib_file.is_file() {
-            try_scan_file(
-                &crate_name,
-                lib_file.as_path(),
-                &resolver,
-                &mut scan_results,
-                sinks,
-            );
-        } else {
+        Err(err) => {
             warn!(
-                "unable to find src dir or lib.rs file; \
-                no files scanned! In crate {:?}",
-                crate_path
+                "Could not resolve module tree ({}); falling back to a single lib.rs file",
+                err
             );
+            let lib_file = crate_path.join(FilePath::new("lib.rs"));
+            if lib_file.is_file() {
+                try_scan_file(
+                    &crate_name,
+                    lib_file.as_path(),
+                    &[],
+                    &resolver,
+                    &mut scan_results,
+                    sinks,
+                    active_cfg,
+                );
+            } else {
+                warn!(
+                    "unable to find src/lib.rs, src/main.rs, or lib.rs; \
+                    no files scanned! In crate {:?}",
+                    crate_path
+                );
+            }
         }
     }
 
@@ -1021,3 +1872,403 @@ pub fn scan_crate_with_sinks(
 pub fn scan_crate(crate_path: &FilePath) -> Result<ScanResults> {
     scan_crate_with_sinks(crate_path, HashSet::new())
 }
+
+/// Name/relative-path pairs declared by an explicit `[[bin]]`/`[[example]]`/
+/// `[[test]]`/`[[bench]]` array in `Cargo.toml`, falling back to the
+/// target's conventional path (`{default_dir}/{name}.rs`) when no `path` key
+/// is given.
+fn named_targets_from_array(
+    table: &Table,
+    array_key: &str,
+    default_dir: &str,
+) -> Vec<(String, String)> {
+    table
+        .get(array_key)
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t.as_table())
+        .filter_map(|t| {
+            let name = t.get("name")?.as_str()?.to_string();
+            let path = t
+                .get("path")
+                .and_then(|p| p.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("{}/{}.rs", default_dir, name));
+            Some((name, path))
+        })
+        .collect()
+}
+
+/// Cargo's autodiscovery fallback for `[[bin]]`/`[[example]]`/`[[test]]`/
+/// `[[bench]]`: every `.rs` file directly inside the conventional directory,
+/// when the manifest doesn't declare any target of that kind explicitly.
+fn autodiscover_targets(crate_path: &FilePath, dir: &str) -> Vec<(String, String)> {
+    let dir_path = crate_path.join(dir);
+    let Ok(entries) = std::fs::read_dir(&dir_path) else {
+        return Vec::new();
+    };
+
+    let mut out: Vec<(String, String)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("rs"))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_string();
+            Some((stem.clone(), format!("{}/{}.rs", dir, stem)))
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+/// Read `Cargo.toml` and resolve every Cargo target in the crate -- the
+/// library, each `[[bin]]` (plus the implicit `src/main.rs` and
+/// autodiscovered `src/bin/*.rs`), `[[example]]`, `[[test]]`, `[[bench]]`,
+/// and the build script -- to its root source file. Used by
+/// `scan_crate_all_targets` so a supply-chain audit can see effects hidden
+/// in test/example/bench code and `build.rs`, not just the library.
+fn resolve_cargo_targets(crate_path: &FilePath) -> Result<Vec<CargoTarget>> {
+    let toml_string = std::fs::read_to_string(crate_path.join("Cargo.toml"))?;
+    let table: Table = toml::from_str(&toml_string)?;
+    let package = table.get("package").and_then(|p| p.as_table());
+
+    let mut targets = Vec::new();
+
+    let lib_path = crate_path.join("src/lib.rs");
+    if lib_path.is_file() {
+        targets.push(CargoTarget {
+            kind: TargetKind::Lib,
+            root: lib_path,
+        });
+    }
+
+    let main_rs = crate_path.join("src/main.rs");
+    if main_rs.is_file() {
+        let crate_name = package
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or("main")
+            .to_string();
+        targets.push(CargoTarget {
+            kind: TargetKind::Bin(crate_name),
+            root: main_rs,
+        });
+    }
+    let mut bins = named_targets_from_array(&table, "bin", "src/bin");
+    if bins.is_empty() {
+        bins = autodiscover_targets(crate_path, "src/bin");
+    }
+    for (name, path) in bins {
+        targets.push(CargoTarget {
+            kind: TargetKind::Bin(name),
+            root: crate_path.join(path),
+        });
+    }
+
+    let kinds: [(&str, &str, fn(String) -> TargetKind); 3] = [
+        ("example", "examples", TargetKind::Example),
+        ("test", "tests", TargetKind::Test),
+        ("bench", "benches", TargetKind::Bench),
+    ];
+    for (array_key, default_dir, wrap) in kinds {
+        let mut entries = named_targets_from_array(&table, array_key, default_dir);
+        if entries.is_empty() {
+            entries = autodiscover_targets(crate_path, default_dir);
+        }
+        for (name, path) in entries {
+            targets.push(CargoTarget {
+                kind: wrap(name),
+                root: crate_path.join(path),
+            });
+        }
+    }
+
+    let build_path = crate_path.join(
+        package
+            .and_then(|p| p.get("build"))
+            .and_then(|b| b.as_str())
+            .unwrap_or("build.rs"),
+    );
+    if build_path.is_file() {
+        targets.push(CargoTarget {
+            kind: TargetKind::BuildScript,
+            root: build_path,
+        });
+    }
+
+    Ok(targets.into_iter().filter(|t| t.root.is_file()).collect())
+}
+
+/// Like `scan_file`, but tagging every effect it records with `target`
+/// instead of leaving it at the scanner's default `TargetKind::Lib`.
+fn scan_file_tagged(
+    crate_name: &str,
+    filepath: &FilePath,
+    mod_prefix: &[String],
+    resolver: &Resolver,
+    scan_results: &mut ScanResults,
+    sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
+    target: TargetKind,
+) -> Result<()> {
+    let mut file = File::open(filepath)?;
+    let mut src = String::new();
+    file.read_to_string(&mut src)?;
+    let syntax_tree = syn::parse_file(&src)?;
+
+    let mut file_resolver = FileResolver::new(crate_name, resolver, filepath)?;
+    let seed_idents: Vec<syn::Ident> = mod_prefix
+        .iter()
+        .map(|seg| syn::Ident::new(seg, proc_macro2::Span::call_site()))
+        .collect();
+    for ident in &seed_idents {
+        file_resolver.push_mod(ident);
+    }
+
+    let mut scanner = Scanner::new_with_cfg(filepath, file_resolver, scan_results, active_cfg);
+    scanner.add_sinks(sinks);
+    scanner.set_target(target);
+    scanner.scan_file(&syntax_tree);
+
+    Ok(())
+}
+
+/// Scan every Cargo target in the crate -- not just the library -- tagging
+/// each effect with the target it was found in (see `TargetKind`). `build.rs`
+/// and `#[cfg(test)]` code are scanned unconditionally here (`test` is
+/// forced on in the active configuration), since a supply-chain audit in
+/// this mode wants to see both even if the caller's own `active_cfg` has
+/// `test` off.
+pub fn scan_crate_all_targets(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
+) -> Result<ScanResults> {
+    let targets = resolve_cargo_targets(crate_path)?;
+    let crate_name = util::load_cargo_toml(crate_path)?.name;
+    let resolver = Resolver::new(crate_path)?;
+
+    let mut target_cfg = active_cfg;
+    target_cfg.test = true;
+
+    let mut results = ScanResults::new();
+    for target in &targets {
+        let files = match resolve_module_tree_from_root(&target.root, &target_cfg) {
+            Ok(files) => files,
+            Err(err) => {
+                warn!(
+                    "Could not resolve module tree for {:?} ({:?}): {}",
+                    target.kind, target.root, err
+                );
+                continue;
+            }
+        };
+
+        for module_file in &files {
+            let mut partial = ScanResults::new();
+            if let Err(err) = scan_file_tagged(
+                &crate_name,
+                &module_file.path,
+                &module_file.mod_path,
+                &resolver,
+                &mut partial,
+                sinks.clone(),
+                target_cfg.clone(),
+                target.kind.clone(),
+            ) {
+                warn!(
+                    "Failed to scan {:?} ({:?}): {}",
+                    target.kind, module_file.path, err
+                );
+                continue;
+            }
+            results.merge(partial);
+        }
+    }
+    results.sort_effects();
+
+    Ok(results)
+}
+
+/// Run `cargo expand` over the crate at `crate_path` and return its stdout
+/// (fully macro-expanded source), falling back to `cargo rustc -- -Zunpretty=expanded`
+/// if `cargo expand` isn't installed. Returns an error if neither succeeds,
+/// which the caller should treat as "macros can't be expanded right now" --
+/// not a hard failure of the scan itself.
+fn expand_crate_source(crate_path: &FilePath) -> Result<String> {
+    let try_command = |mut cmd: std::process::Command| -> Result<String> {
+        let output = cmd.current_dir(crate_path).output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{:?} exited with {}: {}",
+                cmd,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    let mut cargo_expand = std::process::Command::new("cargo");
+    cargo_expand.arg("expand");
+    try_command(cargo_expand).or_else(|_| {
+        let mut cargo_unpretty = std::process::Command::new("cargo");
+        cargo_unpretty.args(["rustc", "--", "-Zunpretty=expanded"]);
+        try_command(cargo_unpretty)
+    })
+}
+
+/// Replace the hygiene placeholder `$crate` that shows up in expanded macro
+/// output with the literal crate name, so the result is valid Rust syntax
+/// `syn::parse_file` can parse (`$crate` on its own is a parse error outside
+/// of a macro body).
+fn normalize_dollar_crate(expanded_src: &str, crate_name: &str) -> String {
+    expanded_src.replace("$crate", crate_name)
+}
+
+/// The name `scan_crate_expanded` writes the expanded source under, inside
+/// `crate_path`, so it has a real on-disk file to hand `FileResolver`/
+/// `Scanner` -- see the doc comment on `scan_crate_expanded` for why this
+/// can't instead be the original source file.
+const EXPANDED_SOURCE_FILE: &str = ".cargo-scan-expanded.rs";
+
+/// Scan `crate_path` twice: once directly, and once over its fully
+/// macro-expanded source (see `expand_crate_source`), so effects hidden
+/// inside macro invocations (FFI calls, unsafe blocks, sink calls wrapped in
+/// a custom macro) are not invisible to the audit.
+///
+/// The two scans necessarily overlap on every call site that isn't inside a
+/// macro, since the expanded source also contains all directly-written
+/// code; an expanded-scan effect is only kept, tagged
+/// `EffectOrigin::Expanded`, when no direct scan already found the same
+/// (caller, callee) pair -- this avoids double-counting both ordinary calls
+/// and calls inside macros expanded more than once (nested/recursive
+/// expansion).
+///
+/// Caveat: `cargo expand`/`-Zunpretty=expanded` emit plain expanded text
+/// with no span information tying it back to the macro invocation that
+/// produced it, so an `Expanded`-origin effect's `call_loc` is a real
+/// line/column into the generated expanded source (written to
+/// `EXPANDED_SOURCE_FILE` so it's on disk and inspectable), not into the
+/// file containing the macro call. Recovering the latter would need the
+/// expansion to come from something that preserves spans (e.g. a proc-macro
+/// host), which this text-based approach doesn't have access to.
+///
+/// If expansion fails (e.g. `cargo expand` isn't installed, or the crate
+/// doesn't build), falls back to the direct-only scan and warns; macro
+/// bodies remain invisible in that case, same as `scan_crate_with_cfg`.
+pub fn scan_crate_expanded(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
+) -> Result<ScanResults> {
+    let mut results = scan_crate_with_cfg(crate_path, sinks.clone(), active_cfg.clone())?;
+
+    let expanded_src = match expand_crate_source(crate_path) {
+        Ok(src) => src,
+        Err(err) => {
+            warn!(
+                "Could not expand macros for {:?} ({}); effects hidden inside macro \
+                calls won't be scanned",
+                crate_path, err
+            );
+            return Ok(results);
+        }
+    };
+
+    let crate_name = util::load_cargo_toml(crate_path)?.name;
+    let normalized = normalize_dollar_crate(&expanded_src, &crate_name);
+    let syntax_tree = match syn::parse_file(&normalized) {
+        Ok(tree) => tree,
+        Err(err) => {
+            warn!(
+                "Could not parse expanded source for {:?}: {}",
+                crate_path, err
+            );
+            return Ok(results);
+        }
+    };
+
+    // `FileResolver`/`Scanner` resolve locations against a real file, not a
+    // directory; write the expanded source out so `Expanded`-origin
+    // effects' `call_loc` points at readable on-disk content instead of
+    // `crate_path` itself.
+    let expanded_path = crate_path.join(EXPANDED_SOURCE_FILE);
+    std::fs::write(&expanded_path, &normalized)?;
+
+    let resolver = Resolver::new(crate_path)?;
+    let file_resolver = FileResolver::new(&crate_name, &resolver, &expanded_path)?;
+    let mut expanded_results = ScanResults::new();
+    let mut scanner = Scanner::new_with_cfg(
+        &expanded_path,
+        file_resolver,
+        &mut expanded_results,
+        active_cfg,
+    );
+    scanner.add_sinks(sinks);
+    scanner.mark_expanded();
+    scanner.scan_file(&syntax_tree);
+
+    // Best-effort cleanup; a leftover expanded-source file doesn't affect
+    // correctness (it isn't `mod`-reachable from the crate root, so `cargo
+    // build` never picks it up), only tidiness.
+    let _ = std::fs::remove_file(&expanded_path);
+
+    let direct_pairs: HashSet<(CanonicalPath, CanonicalPath)> = results
+        .effects
+        .iter()
+        .map(|e| (e.caller().clone(), e.callee().clone()))
+        .collect();
+
+    for ((effect, cfg), target) in expanded_results
+        .effects
+        .into_iter()
+        .zip(expanded_results.effect_cfgs)
+        .zip(expanded_results.effect_targets)
+    {
+        let pair = (effect.caller().clone(), effect.callee().clone());
+        if direct_pairs.contains(&pair) {
+            continue;
+        }
+        results.effects.push(effect);
+        results.effect_cfgs.push(cfg);
+        results.effect_origins.push(EffectOrigin::Expanded);
+        results.effect_targets.push(target);
+    }
+    debug_assert_eq!(results.effects.len(), results.effect_cfgs.len());
+    debug_assert_eq!(results.effects.len(), results.effect_origins.len());
+    debug_assert_eq!(results.effects.len(), results.effect_targets.len());
+    results.sort_effects();
+
+    Ok(results)
+}
+
+/// Scan `crate_path` and save the result to `out_path`, in the format
+/// `ScanResults::load_from_file` expects. Meant to be run once per crate in
+/// a dependency closure (the root crate plus each of its transitive
+/// dependencies), so the per-crate results can later be loaded and `merge`d
+/// -- see `merge_serialized_crates` -- without re-parsing any source.
+pub fn scan_crate_to_file(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    active_cfg: ActiveConfig,
+    out_path: &FilePath,
+) -> Result<()> {
+    scan_crate_with_cfg(crate_path, sinks, active_cfg)?.save_to_file(out_path)
+}
+
+/// Load and `merge` every serialized `ScanResults` in `paths`, reconciling
+/// each crate's call graph (independently built, so its `NodeIndex`es mean
+/// nothing outside its own graph) into one combined graph keyed on
+/// `CanonicalPath`. The result's call graph spans crate boundaries, so
+/// `transitive_callers` can find a caller in one crate reaching a sink
+/// flagged in another.
+pub fn merge_serialized_crates(paths: &[&FilePath]) -> Result<ScanResults> {
+    let mut merged = ScanResults::new();
+    for path in paths {
+        merged.merge(ScanResults::load_from_file(path)?);
+    }
+    merged.sort_effects();
+    Ok(merged)
+}
@@ -11,8 +11,8 @@ pub fn init_logging() {
 
     // wish there was a nicer way to do this, env_logger doesn't make it easy
     // to disable non-cargo_scan logging
-    let filters = "warn,cargo_scan=".to_string()
-        + env::var("RUST_LOG").as_deref().unwrap_or("warn");
+    let filters =
+        "warn,cargo_scan=".to_string() + env::var("RUST_LOG").as_deref().unwrap_or("warn");
 
     Builder::new().parse_filters(&filters).init();
 }
@@ -107,10 +107,20 @@ use std::path::Path;
 use std::str::FromStr;
 use toml::{self, value::Table};
 
+/// Re-exported so callers don't need a direct dependency on `spdx` just to
+/// name the type of [`CrateData::license`].
+pub use spdx::Expression as SpdxExpression;
+
 #[derive(Debug, Clone)]
 pub struct CrateData {
     pub name: String,
     pub version: String,
+    /// The crate's `license` field, parsed as an SPDX license expression
+    /// (e.g. `MIT OR Apache-2.0`). `None` if the field is absent, which is
+    /// the normal case for a crate that instead points at a `license-file`.
+    pub license: Option<SpdxExpression>,
+    /// The crate's `license-file` field, a path relative to the crate root.
+    pub license_file: Option<String>,
 }
 
 pub fn load_cargo_toml(crate_path: &Path) -> Result<CrateData> {
@@ -136,8 +146,25 @@ pub fn load_cargo_toml(crate_path: &Path) -> Result<CrateData> {
         .as_str()
         .context("version field in package couldn't be interpreted as a string")?
         .to_string();
-
-    let result = CrateData { name, version };
+    let license = root_toml_table
+        .get("license")
+        .and_then(|v| v.as_str())
+        .map(|s| {
+            SpdxExpression::parse(s)
+                .with_context(|| format!("couldn't parse license expression `{}`", s))
+        })
+        .transpose()?;
+    let license_file = root_toml_table
+        .get("license-file")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let result = CrateData {
+        name,
+        version,
+        license,
+        license_file,
+    };
     debug!("Loaded: {:?}", result);
     Ok(result)
 }